@@ -0,0 +1,119 @@
+use igloo_interface::{derive::DaDerive, l1::Epoch};
+
+use crate::l1::{attribute::PayloadAttributeImpl, L1Height};
+
+/// Wraps a `DaDerive` source and drops the payloads at or below the highest position
+/// processed so far, ordered by L1 height and then sequence number. This keeps a block
+/// reported by both the instant and the DA source from being derived twice.
+pub struct DedupDaDerive<D> {
+    inner: D,
+    processed: Option<(L1Height, u8)>,
+}
+
+impl<D: DaDerive<Item = PayloadAttributeImpl>> DaDerive for DedupDaDerive<D> {
+    type Item = PayloadAttributeImpl;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let attribute = self.inner.next().await?;
+            let height = attribute.epoch.block_height();
+            if !self.mark_processed(height, attribute.sequence_number) {
+                debug!(
+                    "skip processed attribute at L1 height {} sequence number {}",
+                    height, attribute.sequence_number
+                );
+                continue;
+            }
+            return Some(attribute);
+        }
+    }
+}
+
+impl<D> DedupDaDerive<D> {
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            processed: None,
+        }
+    }
+
+    /// Highest L1 height and sequence number processed so far.
+    pub fn processed(&self) -> Option<(L1Height, u8)> {
+        self.processed
+    }
+
+    /// Records a payload processed outside of this source, e.g. derived instantly.
+    /// Returns `false` if the position was processed already.
+    pub fn mark_processed(&mut self, height: L1Height, sequence_number: u8) -> bool {
+        let position = (height, sequence_number);
+        if self
+            .processed
+            .is_some_and(|processed| position <= processed)
+        {
+            return false;
+        }
+        self.processed = Some(position);
+        true
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::l1::head::L1HeadImpl;
+    use std::collections::VecDeque;
+
+    struct MockSource(VecDeque<PayloadAttributeImpl>);
+
+    impl DaDerive for MockSource {
+        type Item = PayloadAttributeImpl;
+
+        async fn next(&mut self) -> Option<Self::Item> {
+            self.0.pop_front()
+        }
+    }
+
+    fn attribute(height: L1Height, sequence_number: u8) -> PayloadAttributeImpl {
+        let mut attribute = PayloadAttributeImpl::try_from(L1HeadImpl {
+            hash: [height as u8; 32],
+            height,
+            timestamp: height * 12,
+        })
+        .unwrap();
+        attribute.sequence_number = sequence_number;
+        attribute
+    }
+
+    #[tokio::test]
+    async fn overlapping_heights_are_processed_once() {
+        let source = MockSource(
+            [
+                (10, 0),
+                (10, 1),
+                (10, 1),
+                (11, 0),
+                (10, 0),
+                (12, 0),
+                (11, 0),
+            ]
+            .into_iter()
+            .map(|(height, sequence_number)| attribute(height, sequence_number))
+            .collect(),
+        );
+        let mut derive = DedupDaDerive::new(source);
+        // an earlier height derived instantly does not filter later ones
+        assert!(derive.mark_processed(9, 0));
+
+        let mut processed = vec![];
+        while let Some(attribute) = derive.next().await {
+            processed.push((attribute.epoch.block_height(), attribute.sequence_number));
+        }
+        assert_eq!(processed, vec![(10, 0), (10, 1), (11, 0), (12, 0)]);
+        assert_eq!(derive.processed(), Some((12, 0)));
+        assert!(!derive.mark_processed(12, 0));
+    }
+}
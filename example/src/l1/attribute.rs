@@ -5,12 +5,27 @@ use crate::l2::tx::L2Transaction;
 use igloo_interface::l1::{Epoch, PayloadAttribute};
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EpochInfo {
     hash: L1Hash,
     height: L1Height,
     timestamp: L1Timestamp,
 }
 
+impl EpochInfo {
+    pub fn hash(&self) -> L1Hash {
+        self.hash
+    }
+
+    pub fn height(&self) -> L1Height {
+        self.height
+    }
+
+    pub fn timestamp(&self) -> L1Timestamp {
+        self.timestamp
+    }
+}
+
 impl Epoch for EpochInfo {
     type Hash = L1Hash;
     type BlockHeight = L1Height;
@@ -42,7 +57,9 @@ impl TryFrom<L1HeadImpl> for EpochInfo {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PayloadAttributeImpl {
+    /// Serialized as the inner vector.
     pub transactions: Arc<Vec<L2Transaction>>,
     pub epoch: EpochInfo,
     pub sequence_number: u8,
@@ -57,10 +74,18 @@ impl PayloadAttribute for PayloadAttributeImpl {
         self.transactions.clone()
     }
 
+    fn transaction_count(&self) -> usize {
+        self.transactions.len()
+    }
+
     fn epoch_info(&self) -> &Self::Epoch {
         &self.epoch
     }
 
+    fn l1_origin(&self) -> (L1Hash, L1Height) {
+        (self.epoch.hash, self.epoch.height)
+    }
+
     fn sequence_number(&self) -> Self::SequenceNumber {
         self.sequence_number
     }
@@ -77,3 +102,52 @@ impl TryFrom<L1HeadImpl> for PayloadAttributeImpl {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn l1_origin_matches_epoch() {
+        let head = L1HeadImpl {
+            hash: [3; 32],
+            height: 42,
+            timestamp: 1,
+        };
+        let attribute = PayloadAttributeImpl::try_from(head).unwrap();
+        assert_eq!(attribute.l1_origin(), ([3; 32], 42));
+
+        let epoch = attribute.epoch_info();
+        assert_eq!(
+            attribute.l1_origin(),
+            (epoch.block_hash(), epoch.block_height())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let head = L1HeadImpl {
+            hash: [5; 32],
+            height: 7,
+            timestamp: 84,
+        };
+        let mut attribute = PayloadAttributeImpl::try_from(head).unwrap();
+        attribute.sequence_number = 2;
+        attribute.transactions = Arc::new(vec![L2Transaction {
+            from: Default::default(),
+            to: Default::default(),
+            amount: 3,
+            calldata: vec![1, 2, 3],
+        }]);
+
+        let json = serde_json::to_value(&attribute).unwrap();
+        assert!(json["transactions"].is_array());
+        let decoded: PayloadAttributeImpl = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.epoch.hash(), [5; 32]);
+        assert_eq!(decoded.epoch.height(), 7);
+        assert_eq!(decoded.epoch.timestamp(), 84);
+        assert_eq!(decoded.sequence_number, 2);
+        assert_eq!(decoded.transactions, attribute.transactions);
+    }
+}
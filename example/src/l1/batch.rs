@@ -1,13 +1,133 @@
-use igloo_interface::l1::BatchInfo;
+use igloo_interface::{l1::BatchInfo, l2::Transaction};
 
 use super::L1Hash;
+use crate::l2::tx::L2Transaction;
 
-pub struct Batch {}
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Batch {
+    pub root_hash: L1Hash,
+    pub transactions: Vec<L2Transaction>,
+}
 
 impl BatchInfo for Batch {
     type Hash = L1Hash;
 
     fn root_hash(&self) -> Self::Hash {
-        Default::default()
+        self.root_hash
+    }
+}
+
+impl Batch {
+    /// Encodes as `root hash | transaction count (le) | (length (le) | transaction)*`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = self.root_hash.to_vec();
+        buf.extend_from_slice(&(self.transactions.len() as u32).to_le_bytes());
+        for tx in self.transactions.iter() {
+            let encoded = tx.encode();
+            buf.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&encoded);
+        }
+        buf
+    }
+
+    pub fn decode(buf: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader(buf);
+        let root_hash = reader.take(32)?.try_into()?;
+        let count = reader.take_u32()?;
+        let transactions = (0..count)
+            .map(|_| {
+                let len = reader.take_u32()? as usize;
+                L2Transaction::decode(reader.take(len)?)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if !reader.0.is_empty() {
+            anyhow::bail!("{} trailing bytes after batch", reader.0.len());
+        }
+
+        Ok(Self {
+            root_hash,
+            transactions,
+        })
+    }
+
+    /// Encodes and decodes the batch, panicking with the first difference if the result
+    /// does not equal `self`.
+    pub fn assert_roundtrip(&self) {
+        let decoded = Self::decode(&self.encode()).expect("Failed to decode encoded batch");
+        assert_eq!(
+            decoded.root_hash, self.root_hash,
+            "root hash changed after roundtrip"
+        );
+        assert_eq!(
+            decoded.transactions.len(),
+            self.transactions.len(),
+            "transaction count changed after roundtrip"
+        );
+        for (index, (decoded, origin)) in decoded
+            .transactions
+            .iter()
+            .zip(self.transactions.iter())
+            .enumerate()
+        {
+            assert_eq!(
+                decoded, origin,
+                "transaction {index} changed after roundtrip"
+            );
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.0.len() < len {
+            anyhow::bail!("unexpected end of batch, need {} bytes", len);
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn take_u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn batch_roundtrip_works() {
+        let batch = Batch {
+            root_hash: [7; 32],
+            transactions: (0..4)
+                .map(|i| L2Transaction {
+                    from: Pubkey::new_unique(),
+                    to: Pubkey::new_unique(),
+                    amount: i,
+                    calldata: vec![i as u8; i as usize],
+                })
+                .collect(),
+        };
+        batch.assert_roundtrip();
+        Batch::default().assert_roundtrip();
+    }
+
+    #[test]
+    fn batch_decode_rejects_truncated_input() {
+        let batch = Batch {
+            root_hash: [1; 32],
+            transactions: vec![L2Transaction {
+                from: Pubkey::new_unique(),
+                to: Pubkey::new_unique(),
+                amount: 1,
+                calldata: vec![1, 2, 3],
+            }],
+        };
+        let encoded = batch.encode();
+        assert!(Batch::decode(&encoded[..encoded.len() - 1]).is_err());
     }
 }
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use igloo_interface::l1::L1BlockInfo;
+use igloo_interface::l1::{Epoch, L1BlockInfo, PayloadAttribute};
 
 use crate::l2::tx::L2Transaction;
 
@@ -8,7 +8,8 @@ use super::{attribute::PayloadAttributeImpl, batch, head::L1HeadImpl, tx};
 
 pub struct L1BlockInfoImpl {
     pub deposit_txs: Vec<tx::DepositTx>,
-    pub batch: Option<batch::Batch>,
+    /// Batches posted in the block, in posting order.
+    pub batches: Vec<batch::Batch>,
     pub l1_head: L1HeadImpl,
 }
 
@@ -21,8 +22,8 @@ impl L1BlockInfo<PayloadAttributeImpl> for L1BlockInfoImpl {
         self.deposit_txs.as_slice()
     }
 
-    fn batch_info(&self) -> Option<&Self::Batch> {
-        self.batch.as_ref()
+    fn batches(&self) -> &[Self::Batch] {
+        &self.batches
     }
 
     fn l1_head(&self) -> &Self::L1Head {
@@ -33,19 +34,267 @@ impl L1BlockInfo<PayloadAttributeImpl> for L1BlockInfoImpl {
 impl TryInto<PayloadAttributeImpl> for L1BlockInfoImpl {
     type Error = anyhow::Error;
 
+    /// Merges deposits and the transactions of every batch into a single payload, see
+    /// `into_payloads` to derive one payload per batch instead.
     fn try_into(self) -> Result<PayloadAttributeImpl, Self::Error> {
-        let deposit_tx = self
+        let mut payloads = self.into_payloads()?.into_iter();
+        let mut merged = payloads
+            .next()
+            .ok_or(anyhow::anyhow!("no payload derived"))?;
+        let mut transactions = (*merged.transactions).clone();
+        for payload in payloads {
+            transactions.extend(payload.transactions.iter().cloned());
+        }
+        merged.transactions = Arc::new(transactions);
+        Ok(merged)
+    }
+}
+
+impl L1BlockInfoImpl {
+    /// Derives one payload per batch of the block, in posting order, with increasing
+    /// sequence numbers within the epoch of the block. Deposits are included first in the
+    /// payload of the first batch. A block without batches derives a single payload
+    /// holding only its deposits.
+    pub fn into_payloads(self) -> anyhow::Result<Vec<PayloadAttributeImpl>> {
+        let mut deposit_txs = self
             .deposit_txs
             .into_iter()
-            .map(|tx| tx.try_into())
+            .enumerate()
+            .map(|(index, tx)| {
+                tx.try_into()
+                    .map_err(|e| anyhow::anyhow!("invalid deposit {index}: {e}"))
+            })
             .collect::<anyhow::Result<Vec<L2Transaction>>>()?;
         let epoch = self.l1_head.try_into()?;
-        // TODO: derive batch from `self.batch` if needed later
 
-        Ok(PayloadAttributeImpl {
-            transactions: Arc::new(deposit_tx),
-            epoch,
-            sequence_number: 0,
+        let mut batches = self.batches.into_iter();
+        let mut first = batches.next().unwrap_or_default().transactions;
+        deposit_txs.append(&mut first);
+        std::iter::once(deposit_txs)
+            .chain(batches.map(|batch| batch.transactions))
+            .enumerate()
+            .map(|(sequence_number, transactions)| -> anyhow::Result<_> {
+                Ok(PayloadAttributeImpl {
+                    transactions: Arc::new(transactions),
+                    epoch: epoch.clone(),
+                    sequence_number: sequence_number.try_into()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Reconstructs the block a payload attribute was derived from, as far as possible:
+    /// the first `deposit_count` transactions of `attribute` become the deposits, the
+    /// remaining ones a single batch with a default root hash. This is only exact if the
+    /// deposits precede the sequenced transactions, which holds for the first payload of
+    /// an epoch as derived by `into_payloads`, the only one carrying deposits.
+    pub fn from_attribute(
+        attribute: &PayloadAttributeImpl,
+        head: L1HeadImpl,
+        deposit_count: usize,
+    ) -> anyhow::Result<Self> {
+        if attribute.l1_origin() != (head.hash, head.height) {
+            anyhow::bail!(
+                "payload attribute derived from L1 block {}, not {}",
+                attribute.epoch.block_height(),
+                head.height
+            );
+        }
+        if deposit_count > 0 && attribute.sequence_number != 0 {
+            anyhow::bail!(
+                "only the first payload of an epoch carries deposits, got sequence number {}",
+                attribute.sequence_number
+            );
+        }
+        if deposit_count > attribute.transactions.len() {
+            anyhow::bail!(
+                "{} deposits requested but the payload only holds {} transactions",
+                deposit_count,
+                attribute.transactions.len()
+            );
+        }
+
+        let (deposits, sequenced) = attribute.transactions.split_at(deposit_count);
+        let deposit_txs = deposits
+            .iter()
+            .map(|tx| tx::DepositTx {
+                from: tx.from,
+                to: tx.to,
+                amount: tx.amount,
+                calldata: tx.calldata.clone(),
+            })
+            .collect();
+        let batches = if sequenced.is_empty() {
+            vec![]
+        } else {
+            vec![batch::Batch {
+                root_hash: Default::default(),
+                transactions: sequenced.to_vec(),
+            }]
+        };
+
+        Ok(Self {
+            deposit_txs,
+            batches,
+            l1_head: head,
         })
     }
 }
+
+/// Converts a range of L1 blocks into payloads, one per batch of every block, keeping the
+/// deposit and batch order of every block. Blocks must be ordered by strictly increasing
+/// height.
+pub fn derive_payloads(blocks: Vec<L1BlockInfoImpl>) -> anyhow::Result<Vec<PayloadAttributeImpl>> {
+    if let Some(pair) = blocks
+        .windows(2)
+        .find(|pair| pair[0].l1_head.height >= pair[1].l1_head.height)
+    {
+        anyhow::bail!(
+            "L1 blocks out of order: {} followed by {}",
+            pair[0].l1_head.height,
+            pair[1].l1_head.height
+        );
+    }
+
+    let payloads = blocks
+        .into_iter()
+        .map(L1BlockInfoImpl::into_payloads)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(payloads.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn block(height: u64, deposits: u64) -> L1BlockInfoImpl {
+        L1BlockInfoImpl {
+            deposit_txs: (1..=deposits)
+                .map(|amount| tx::DepositTx {
+                    from: Pubkey::new_unique(),
+                    to: Pubkey::new_unique(),
+                    amount,
+                    calldata: vec![],
+                })
+                .collect(),
+            batches: vec![],
+            l1_head: L1HeadImpl {
+                hash: [height as u8; 32],
+                height,
+                timestamp: height * 12,
+            },
+        }
+    }
+
+    #[test]
+    fn derive_payloads_keeps_epochs() {
+        let payloads = derive_payloads(vec![block(10, 2), block(11, 0), block(12, 3)]).unwrap();
+
+        assert_eq!(payloads.len(), 3);
+        for (payload, (height, deposits)) in payloads.iter().zip([(10, 2), (11, 0), (12, 3)]) {
+            assert_eq!(payload.epoch_info().block_height(), height);
+            assert_eq!(payload.epoch_info().block_hash(), [height as u8; 32]);
+            assert_eq!(payload.sequence_number(), 0);
+            let amounts = payload
+                .transactions()
+                .iter()
+                .map(|tx| tx.amount)
+                .collect::<Vec<_>>();
+            assert_eq!(amounts, (1..=deposits).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn derive_payloads_from_multiple_batches() {
+        let batch = |amounts: std::ops::Range<u64>| batch::Batch {
+            root_hash: Default::default(),
+            transactions: amounts
+                .map(|amount| L2Transaction {
+                    from: Pubkey::new_unique(),
+                    to: Pubkey::new_unique(),
+                    amount,
+                    calldata: vec![],
+                })
+                .collect(),
+        };
+        let mut l1_block = block(10, 2);
+        l1_block.batches = vec![batch(100..102), batch(200..203)];
+
+        let payloads = derive_payloads(vec![l1_block, block(11, 1)]).unwrap();
+        let derived = payloads
+            .iter()
+            .map(|payload| {
+                (
+                    payload.epoch_info().block_height(),
+                    payload.sequence_number(),
+                    payload
+                        .transactions()
+                        .iter()
+                        .map(|tx| tx.amount)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            derived,
+            vec![
+                (10, 0, vec![1, 2, 100, 101]),
+                (10, 1, vec![200, 201, 202]),
+                (11, 0, vec![1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_attribute_roundtrip() {
+        let mut l1_block = block(10, 2);
+        l1_block.batches = vec![batch::Batch {
+            root_hash: Default::default(),
+            transactions: vec![L2Transaction {
+                from: Pubkey::new_unique(),
+                to: Pubkey::new_unique(),
+                amount: 100,
+                calldata: vec![1, 2],
+            }],
+        }];
+        let deposits = l1_block.deposit_txs.clone();
+        let batches = l1_block.batches.clone();
+        let head = l1_block.l1_head.clone();
+        let attribute: PayloadAttributeImpl = l1_block.try_into().unwrap();
+
+        let rebuilt = L1BlockInfoImpl::from_attribute(&attribute, head.clone(), 2).unwrap();
+        assert_eq!(rebuilt.batches, batches);
+        assert_eq!(rebuilt.l1_head.height, head.height);
+        let amounts = |txs: &[tx::DepositTx]| {
+            txs.iter()
+                .map(|tx| (tx.from, tx.to, tx.amount))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(amounts(&rebuilt.deposit_txs), amounts(&deposits));
+
+        assert!(L1BlockInfoImpl::from_attribute(&attribute, head.clone(), 4).is_err());
+        assert!(L1BlockInfoImpl::from_attribute(&attribute, block(11, 0).l1_head, 2).is_err());
+    }
+
+    #[test]
+    fn malformed_deposits_are_rejected() {
+        let mut l1_block = block(10, 3);
+        l1_block.deposit_txs[1].amount = 0;
+        let err = l1_block.into_payloads().unwrap_err();
+        assert!(err.to_string().contains("invalid deposit 1"));
+
+        let mut l1_block = block(10, 3);
+        l1_block.deposit_txs[2].to = Pubkey::default();
+        let result: anyhow::Result<PayloadAttributeImpl> = l1_block.try_into();
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("invalid deposit 2"));
+    }
+
+    #[test]
+    fn derive_payloads_rejects_unordered_blocks() {
+        assert!(derive_payloads(vec![block(11, 0), block(10, 0)]).is_err());
+    }
+}
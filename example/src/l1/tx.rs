@@ -1,6 +1,7 @@
 use igloo_interface::l1::DepositTransaction;
 use solana_sdk::pubkey::Pubkey;
 
+#[derive(Debug, Clone)]
 pub struct DepositTx {
     pub from: Pubkey,
     pub to: Pubkey,
@@ -29,3 +30,17 @@ impl DepositTransaction for DepositTx {
         &self.calldata
     }
 }
+
+impl DepositTx {
+    /// Rejects deposits that would mint nothing or send funds to the default pubkey,
+    /// which is the zero-initialized value of a recipient that was never set.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.amount == 0 {
+            anyhow::bail!("zero amount deposited to {}", self.to);
+        }
+        if self.to == Pubkey::default() {
+            anyhow::bail!("deposit from {} has no recipient", self.from);
+        }
+        Ok(())
+    }
+}
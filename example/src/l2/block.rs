@@ -1,7 +1,32 @@
-use igloo_interface::l2::{Block, BlockPayload, Entry};
-use solana_sdk::transaction::VersionedTransaction;
+use igloo_interface::{
+    l1::PayloadAttribute,
+    l2::{Block, BlockPayload, Entry, Transaction},
+};
+use solana_sdk::{hash::Hasher, transaction::VersionedTransaction};
 
-use super::head::L2HeadImpl;
+use crate::l1::attribute::PayloadAttributeImpl;
+
+use super::{head::L2HeadImpl, L2Hash};
+
+/// Hash of the block built on `parent` from `attribute`, so it can be predicted from the
+/// payload alone. It is the SHA-256 of `parent | L1 origin hash | L1 origin height (le) |
+/// sequence number | transaction count (u32, le)`, followed by every transaction as its
+/// encoded length (u32, le) and its encoding, in order.
+pub fn block_hash(parent: L2Hash, attribute: &PayloadAttributeImpl) -> L2Hash {
+    let (origin_hash, origin_height) = attribute.l1_origin();
+    let mut hasher = Hasher::default();
+    hasher.hash(parent.as_ref());
+    hasher.hash(&origin_hash);
+    hasher.hash(&origin_height.to_le_bytes());
+    hasher.hash(&[attribute.sequence_number]);
+    hasher.hash(&(attribute.transactions.len() as u32).to_le_bytes());
+    for tx in attribute.transactions.iter() {
+        let encoded = tx.encode();
+        hasher.hash(&(encoded.len() as u32).to_le_bytes());
+        hasher.hash(&encoded);
+    }
+    hasher.result()
+}
 
 #[derive(Clone)]
 pub struct SimpleEntry {
@@ -62,3 +87,49 @@ impl From<BlockPayloadImpl> for BlockImpl {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{l1::head::L1HeadImpl, l2::tx::L2Transaction};
+    use solana_sdk::pubkey::Pubkey;
+    use std::sync::Arc;
+
+    fn attribute(txs: Vec<L2Transaction>) -> PayloadAttributeImpl {
+        let mut attribute = PayloadAttributeImpl::try_from(L1HeadImpl {
+            hash: [1; 32],
+            height: 1,
+            timestamp: 0,
+        })
+        .unwrap();
+        attribute.transactions = Arc::new(txs);
+        attribute
+    }
+
+    #[test]
+    fn block_hash_is_deterministic() {
+        let txs = (1..=2)
+            .map(|amount| L2Transaction {
+                from: Pubkey::new_unique(),
+                to: Pubkey::new_unique(),
+                amount,
+                calldata: vec![],
+            })
+            .collect::<Vec<_>>();
+        let parent = L2Hash::new_unique();
+
+        let hash = block_hash(parent, &attribute(txs.clone()));
+        assert_eq!(hash, block_hash(parent, &attribute(txs.clone())));
+
+        assert_ne!(
+            hash,
+            block_hash(L2Hash::new_unique(), &attribute(txs.clone()))
+        );
+        assert_ne!(hash, block_hash(parent, &attribute(txs[..1].to_vec())));
+        let reversed = txs.iter().rev().cloned().collect();
+        assert_ne!(hash, block_hash(parent, &attribute(reversed)));
+        let mut next = attribute(txs);
+        next.sequence_number += 1;
+        assert_ne!(hash, block_hash(parent, &next));
+    }
+}
@@ -108,4 +108,10 @@ impl SimpleStore {
         self.inner.insert_shreds(all_shreds, None, false)?;
         Ok(num_data)
     }
+
+    /// Removes the entries of the slots from `from_slot` to `to_slot`, both included, so
+    /// the slots can be written again.
+    pub(crate) fn purge_slots(&self, from_slot: Slot, to_slot: Slot) {
+        self.inner.purge_and_compact_slots(from_slot, to_slot);
+    }
 }
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use solana_sdk::signature::Keypair;
+use solana_sdk::{account::AccountSharedData, pubkey::Pubkey, signature::Keypair};
 use std::{path::Path, sync::Arc};
 use tokio::sync::{mpsc::Sender, RwLock};
 
@@ -14,15 +14,20 @@ use super::{
     ledger::SharedLedger,
     producer::SvmProducer,
     stream::{SharedStream, TransactionStreamImpl},
+    tx::L2Transaction,
     L2Height,
 };
 
+/// Compute units a block may consume, the same as the Solana block cost limit.
+pub const DEFAULT_BLOCK_COMPUTE_LIMIT: u64 = 48_000_000;
+
 pub struct SvmEngine {
     stream: SharedStream,
     producer: SvmProducer,
     ledger: SharedLedger,
     blockstore: SharedStore,
     attribute_sender: Sender<PayloadAttributeImpl>,
+    block_compute_limit: u64,
 }
 
 impl SvmEngine {
@@ -41,9 +46,13 @@ impl SvmEngine {
             ledger,
             blockstore,
             attribute_sender,
+            block_compute_limit: DEFAULT_BLOCK_COMPUTE_LIMIT,
         })
     }
 
+    /// Produces a block from the deposits of `attribute` followed by a batch of the pool.
+    /// Only the included transactions are sent as attribute, pool transactions deferred
+    /// over the block compute limit are back in the pool for a later block.
     pub async fn produce_block(
         &mut self,
         attribute: PayloadAttributeImpl,
@@ -61,21 +70,73 @@ impl SvmEngine {
             transactions.len(),
             extra_txs.len()
         );
+        let deposits = transactions.len();
         transactions.extend(extra_txs);
 
         let new_attribute = PayloadAttributeImpl {
-            transactions: Arc::new(transactions),
+            transactions: Arc::new(transactions.clone()),
             epoch: attribute.epoch,
             sequence_number: attribute.sequence_number,
         };
-        let block = self.producer.produce(new_attribute.clone()).await?;
+        let (block, requeued, dropped) = self
+            .execute_payload(new_attribute.clone(), deposits, self.block_compute_limit)
+            .await?;
+        // deferred transactions are a suffix of the payload
+        transactions.truncate(transactions.len() - requeued.len() - dropped.len());
+        let included = PayloadAttributeImpl {
+            transactions: Arc::new(transactions),
+            ..new_attribute
+        };
 
-        if let Err(e) = self.attribute_sender.send(new_attribute).await {
+        if let Err(e) = self.attribute_sender.send(included).await {
             error!("Failed to send attribute: {}", e);
         }
 
         Ok(block)
     }
+
+    /// Executes the transactions of `attribute` into a block payload, including them in
+    /// order only while the compute units they consume stay within `block_compute_limit`.
+    /// The first `deposits` transactions are derived from L1 and always included, so only
+    /// pool transactions are deferred. The deferred transactions are returned to the pool
+    /// and reported along with the payload, followed by the deferred transactions the pool
+    /// rejected, e.g. because it filled up in the meantime, which are dropped.
+    pub async fn execute_payload(
+        &mut self,
+        attribute: PayloadAttributeImpl,
+        deposits: usize,
+        block_compute_limit: u64,
+    ) -> anyhow::Result<(BlockPayloadImpl, Vec<L2Transaction>, Vec<L2Transaction>)> {
+        let (block, deferred) = self
+            .producer
+            .produce_with_limit(attribute, deposits, block_compute_limit)
+            .await?;
+
+        if !deferred.is_empty() {
+            debug!(
+                "block compute limit {} reached, defer {} txs",
+                block_compute_limit,
+                deferred.len()
+            );
+        }
+        let mut requeued = Vec::with_capacity(deferred.len());
+        let mut dropped = vec![];
+        let mut stream = self.stream.write().await;
+        for tx in deferred {
+            match stream.try_insert(tx.clone()) {
+                Ok(_) => requeued.push(tx),
+                Err(e) => {
+                    warn!(
+                        "Drop deferred tx {:?}, failed to return it to the pool: {}",
+                        tx, e
+                    );
+                    dropped.push(tx);
+                }
+            }
+        }
+
+        Ok((block, requeued, dropped))
+    }
 }
 
 impl Engine for SvmEngine {
@@ -89,10 +150,18 @@ impl Engine for SvmEngine {
 
     type BlockHeight = L2Height;
 
+    type Pubkey = Pubkey;
+
+    type AccountSharedData = AccountSharedData;
+
     fn stream(&self) -> &SharedStream {
         &self.stream
     }
 
+    fn account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.producer.account(pubkey)
+    }
+
     async fn get_head(&mut self, height: Self::BlockHeight) -> Result<Option<Self::Head>> {
         Ok(self
             .ledger
@@ -125,11 +194,187 @@ impl EngineApi<BlockImpl, L2HeadImpl> for SvmEngine {
         Ok(head)
     }
 
-    async fn reorg(&mut self, _reset_to: L2HeadImpl) -> Result<()> {
-        todo!()
+    /// Drops the blocks after `reset_to` from the ledger and the blockstore. Executed
+    /// transactions are not committed to the bank, so it holds no state of the dropped
+    /// blocks to revert.
+    async fn reorg(&mut self, reset_to: L2HeadImpl) -> Result<()> {
+        let removed = self.ledger.write().await.reset_to(&reset_to)?;
+        if let Some(last) = removed.last() {
+            self.blockstore
+                .write()
+                .await
+                .purge_slots(reset_to.block_height() + 1, last.head().block_height());
+        }
+        debug!(
+            "reorg to height: {}, {} blocks dropped",
+            reset_to.block_height(),
+            removed.len()
+        );
+        Ok(())
     }
 
     async fn finalize(&mut self, _block: L2HeadImpl) -> Result<()> {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{l1::head::L1HeadImpl, l2::block::block_hash};
+    use solana_sdk::{clock::Clock, sysvar::SysvarId};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn account_returns_l2_state() -> Result<()> {
+        let base_path = tempfile::tempdir()?.into_path();
+        let (attribute_sender, _attribute_receiver) = mpsc::channel(1);
+        let engine = SvmEngine::new(&base_path, attribute_sender)?;
+
+        assert!(engine.account(&Clock::id()).is_some());
+        assert!(engine.account(&Pubkey::new_unique()).is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn execute_payload_defers_transactions_beyond_limit() -> Result<()> {
+        let base_path = tempfile::tempdir()?.into_path();
+        let (attribute_sender, _attribute_receiver) = mpsc::channel(1);
+        let mut engine = SvmEngine::new(&base_path, attribute_sender)?;
+
+        let txs = (1..=3)
+            .map(|amount| L2Transaction {
+                from: Pubkey::new_unique(),
+                to: Pubkey::new_unique(),
+                amount,
+                calldata: vec![],
+            })
+            .collect::<Vec<_>>();
+        let attribute = |txs: Vec<L2Transaction>| -> Result<PayloadAttributeImpl> {
+            let mut attribute = PayloadAttributeImpl::try_from(L1HeadImpl {
+                hash: Default::default(),
+                height: 1,
+                timestamp: 0,
+            })?;
+            attribute.transactions = Arc::new(txs);
+            Ok(attribute)
+        };
+
+        let (block, deferred, dropped) = engine
+            .execute_payload(attribute(txs[..1].to_vec())?, 0, u64::MAX)
+            .await?;
+        assert_eq!(block.entries.len(), 1);
+        assert!(deferred.is_empty());
+        assert!(dropped.is_empty());
+        assert_eq!(
+            block.head.hash,
+            block_hash(Default::default(), &attribute(txs[..1].to_vec())?)
+        );
+        let (_, units) = engine.producer.process_single_tx(&txs[0]).await?;
+
+        // room for two transactions only
+        let (block, deferred, dropped) = engine
+            .execute_payload(attribute(txs.clone())?, 0, units * 2 + units / 2)
+            .await?;
+        let included = block.entries.iter().map(|e| e.tx_count()).sum::<usize>();
+        assert_eq!(included, 2);
+        assert_eq!(
+            block.head.hash,
+            block_hash(Default::default(), &attribute(txs[..2].to_vec())?)
+        );
+        assert_eq!(deferred, txs[2..].to_vec());
+        assert!(dropped.is_empty());
+        assert_eq!(engine.stream().read().await.transactions, deferred);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn execute_payload_reports_deferred_transactions_dropped_by_full_pool() -> Result<()> {
+        let base_path = tempfile::tempdir()?.into_path();
+        let (attribute_sender, _attribute_receiver) = mpsc::channel(1);
+        let mut engine = SvmEngine::new(&base_path, attribute_sender)?;
+
+        let tx = |amount| L2Transaction {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount,
+            calldata: vec![],
+        };
+        let txs = (1..=3).map(tx).collect::<Vec<_>>();
+        let (_, units) = engine.producer.process_single_tx(&tx(1)).await?;
+        // room for one more transaction in the pool
+        let pending = tx(4);
+        *engine.stream.write().await = TransactionStreamImpl::new(2);
+        engine.stream.write().await.try_insert(pending.clone())?;
+
+        let mut attribute = PayloadAttributeImpl::try_from(L1HeadImpl {
+            hash: Default::default(),
+            height: 1,
+            timestamp: 0,
+        })?;
+        attribute.transactions = Arc::new(txs.clone());
+        // room for one transaction in the block
+        let (block, deferred, dropped) = engine
+            .execute_payload(attribute, 0, units + units / 2)
+            .await?;
+        let included = block.entries.iter().map(|e| e.tx_count()).sum::<usize>();
+        assert_eq!(included, 1);
+        assert_eq!(deferred, txs[1..2].to_vec());
+        assert_eq!(dropped, txs[2..].to_vec());
+        assert_eq!(
+            engine.stream().read().await.transactions,
+            [pending, txs[1].clone()]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn produce_block_sends_only_included_transactions() -> Result<()> {
+        let base_path = tempfile::tempdir()?.into_path();
+        let (attribute_sender, mut attribute_receiver) = mpsc::channel(2);
+        let mut engine = SvmEngine::new(&base_path, attribute_sender)?;
+
+        let tx = |amount| L2Transaction {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount,
+            calldata: vec![],
+        };
+        let attribute = |deposits: Vec<L2Transaction>| -> Result<PayloadAttributeImpl> {
+            let mut attribute = PayloadAttributeImpl::try_from(L1HeadImpl {
+                hash: Default::default(),
+                height: 1,
+                timestamp: 0,
+            })?;
+            attribute.transactions = Arc::new(deposits);
+            Ok(attribute)
+        };
+        let (_, units) = engine.producer.process_single_tx(&tx(1)).await?;
+        let pool = (2..=4).map(tx).collect::<Vec<_>>();
+        for tx in pool.iter() {
+            engine.stream.write().await.try_insert(tx.clone())?;
+        }
+
+        // room for the deposit and one pool transaction
+        engine.block_compute_limit = units * 2 + units / 2;
+        let deposit = tx(1);
+        let block = engine
+            .produce_block(attribute(vec![deposit.clone()])?)
+            .await?;
+        let sent = attribute_receiver.recv().await.unwrap();
+        assert_eq!(*sent.transactions, vec![deposit, pool[0].clone()]);
+        assert_eq!(block.head.hash, block_hash(Default::default(), &sent));
+        assert_eq!(engine.stream().read().await.transactions, pool[1..]);
+
+        // deposits are included beyond the limit and never returned to the pool
+        engine.block_compute_limit = units / 2;
+        let deposits = vec![tx(5), tx(6)];
+        let block = engine.produce_block(attribute(deposits.clone())?).await?;
+        let sent = attribute_receiver.recv().await.unwrap();
+        assert_eq!(*sent.transactions, deposits);
+        let included = block.entries.iter().map(|e| e.tx_count()).sum::<usize>();
+        assert_eq!(included, 2);
+        assert_eq!(engine.stream().read().await.transactions, pool[1..]);
+        Ok(())
+    }
+}
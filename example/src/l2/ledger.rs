@@ -28,4 +28,29 @@ impl MockLedger {
     pub fn latest_height(&self) -> L2Height {
         self.blocks.last_key_value().map(|(k, _)| *k).unwrap_or(0)
     }
+
+    /// Removes the blocks after `head` and returns them in height order, fails if `head` is
+    /// not a block of the ledger.
+    pub fn reset_to(&mut self, head: &L2HeadImpl) -> anyhow::Result<Vec<BlockImpl>> {
+        if self.hash_map.get(&head.block_hash()) != Some(&head.block_height()) {
+            anyhow::bail!(
+                "Unknown block {} at height {}",
+                head.block_hash(),
+                head.block_height()
+            );
+        }
+        let removed = self.blocks.split_off(&(head.block_height() + 1));
+        for block in removed.values() {
+            self.hash_map.remove(&block.head().block_hash());
+        }
+        Ok(removed.into_values().collect())
+    }
+
+    /// Hash of the latest block, the default hash without blocks.
+    pub fn latest_hash(&self) -> L2Hash {
+        self.blocks
+            .last_key_value()
+            .map(|(_, block)| block.head().block_hash())
+            .unwrap_or_default()
+    }
 }
@@ -4,9 +4,11 @@ pub mod blockstore;
 pub mod engine;
 pub mod head;
 pub mod ledger;
+pub mod persistent_stream;
 pub mod producer;
 pub mod stream;
 pub mod tx;
+pub mod validation;
 
 pub type L2Hash = solana_sdk::hash::Hash;
 pub type L2Height = u64;
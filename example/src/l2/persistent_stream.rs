@@ -0,0 +1,433 @@
+use solana_sdk::hash::Hash;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use igloo_interface::l2::{
+    stream::{batch_len, is_oversized, BatchId, TransactionStream},
+    Transaction,
+};
+
+use super::{stream::SimpleBatchSettings, tx::L2Transaction};
+
+/// A transaction stream that keeps pending transactions in an append-only log on disk,
+/// so they survive a sequencer restart. Each record is the length of the encoded
+/// transaction (u32, little endian) followed by the encoded transaction.
+///
+/// In-flight transactions stay in the log until their batch is finalized, so a restart
+/// requeues them. Transactions leave the log by rewriting it. If that fails, `next_batch`
+/// takes no transactions and `finalize_batch` keeps the batch in-flight and returns false,
+/// so a restart never batches them a second time. Oversized transactions are dropped
+/// from the log before a batch is taken, see `is_oversized`.
+pub struct PersistentStream {
+    path: PathBuf,
+    log: File,
+    transactions: Vec<L2Transaction>,
+    inflight: BTreeMap<BatchId, Vec<L2Transaction>>,
+    /// Ids of the pending and in-flight transactions.
+    ids: HashSet<Hash>,
+    next_batch_id: BatchId,
+}
+
+impl PersistentStream {
+    /// Opens the log at `path`, reloading all pending transactions if it already exists.
+    pub fn new(path: &Path) -> Result<Self> {
+        let transactions = if path.exists() {
+            Self::load(path)?
+        } else {
+            vec![]
+        };
+
+        let mut stream = Self {
+            path: path.to_path_buf(),
+            log: Self::open_log(path)?,
+            ids: transactions.iter().map(L2Transaction::id).collect(),
+            transactions,
+            inflight: Default::default(),
+            next_batch_id: 0,
+        };
+        // drop a torn record left by an interrupted append
+        stream.compact()?;
+        Ok(stream)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the transaction with the given `L2Transaction::id` is pending or in-flight.
+    pub fn contains(&self, id: &Hash) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Drops the ids of transactions leaving the stream for good.
+    fn forget(&mut self, batch: &[L2Transaction]) {
+        for tx in batch {
+            self.ids.remove(&tx.id());
+        }
+    }
+
+    fn load(path: &Path) -> Result<Vec<L2Transaction>> {
+        let mut buf = vec![];
+        File::open(path)?.read_to_end(&mut buf)?;
+
+        let mut transactions = vec![];
+        let mut offset = 0;
+        while offset + 4 <= buf.len() {
+            let len = u32::from_le_bytes(buf[offset..offset + 4].try_into()?) as usize;
+            let end = offset + 4 + len;
+            if end > buf.len() {
+                warn!("ignore truncated record at offset {} of {:?}", offset, path);
+                break;
+            }
+            transactions.push(L2Transaction::decode(&buf[offset + 4..end])?);
+            offset = end;
+        }
+        Ok(transactions)
+    }
+
+    fn open_log(path: &Path) -> Result<File> {
+        Ok(OpenOptions::new().create(true).append(true).open(path)?)
+    }
+
+    fn append(&mut self, tx: &L2Transaction) -> Result<()> {
+        self.log.write_all(&Self::record(tx))?;
+        self.log.sync_data()?;
+        Ok(())
+    }
+
+    /// Rewrites the log so it contains only the in-flight and pending transactions.
+    fn compact(&mut self) -> Result<()> {
+        let tmp_path = self.path.with_extension("compact");
+        let mut file = File::create(&tmp_path)?;
+        for tx in self
+            .inflight
+            .values()
+            .flatten()
+            .chain(self.transactions.iter())
+        {
+            file.write_all(&Self::record(tx))?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        self.log = Self::open_log(&self.path)?;
+        Ok(())
+    }
+
+    /// Drops the pending transactions that never fit into a batch of `settings`, they are
+    /// kept if the log can not be rewritten.
+    fn evict_oversized(&mut self, settings: &SimpleBatchSettings) {
+        if !self
+            .transactions
+            .iter()
+            .any(|tx| is_oversized(tx, settings))
+        {
+            return;
+        }
+        let (oversized, pending) = self
+            .transactions
+            .iter()
+            .cloned()
+            .partition::<Vec<_>, _>(|tx| is_oversized(tx, settings));
+        let transactions = std::mem::replace(&mut self.transactions, pending);
+        if let Err(e) = self.compact() {
+            error!(
+                "Failed to compact transaction log {:?}, keep oversized transactions: {}",
+                self.path, e
+            );
+            self.transactions = transactions;
+            return;
+        }
+        for tx in oversized.iter() {
+            warn!(
+                "Drop transaction {:?}, it exceeds the batch limit of {} bytes",
+                tx, settings.max_bytes
+            );
+        }
+        self.forget(&oversized);
+    }
+
+    fn record(tx: &L2Transaction) -> Vec<u8> {
+        let encoded = tx.encode();
+        let mut record = Vec::with_capacity(4 + encoded.len());
+        record.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        record.extend_from_slice(&encoded);
+        record
+    }
+}
+
+impl TransactionStream for PersistentStream {
+    type TxIn = L2Transaction;
+    type TxOut = L2Transaction;
+    type Settings = SimpleBatchSettings;
+    type Error = anyhow::Error;
+
+    async fn insert(&mut self, tx: Self::TxIn) -> Result<()> {
+        let id = tx.id();
+        if self.contains(&id) {
+            anyhow::bail!("transaction {id} already pending");
+        }
+        self.append(&tx)?;
+        self.ids.insert(id);
+        self.transactions.push(tx);
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    async fn next_batch(&mut self, settings: Self::Settings) -> Vec<Self::TxOut> {
+        self.evict_oversized(&settings);
+        let size = batch_len(&self.transactions, &settings);
+        if size == 0 {
+            return vec![];
+        }
+        let batch = self.transactions.drain(..size).collect::<Vec<_>>();
+        if let Err(e) = self.compact() {
+            error!(
+                "Failed to compact transaction log {:?}, keep the batch pending: {}",
+                self.path, e
+            );
+            self.transactions.splice(..0, batch);
+            return vec![];
+        }
+        self.forget(&batch);
+        batch
+    }
+
+    fn peek_batch(&self, settings: Self::Settings) -> Vec<&Self::TxOut> {
+        let fitting = self
+            .transactions
+            .iter()
+            .filter(|tx| !is_oversized(*tx, &settings))
+            .collect::<Vec<_>>();
+        let size = batch_len(fitting.iter().copied(), &settings);
+        fitting[..size].to_vec()
+    }
+
+    async fn take_batch_inflight(
+        &mut self,
+        settings: Self::Settings,
+    ) -> (BatchId, Vec<Self::TxOut>) {
+        self.evict_oversized(&settings);
+        let size = batch_len(&self.transactions, &settings);
+        let batch = self.transactions.drain(..size).collect::<Vec<_>>();
+        let id = self.next_batch_id;
+        self.next_batch_id += 1;
+        self.inflight.insert(id, batch.clone());
+        (id, batch)
+    }
+
+    async fn finalize_batch(&mut self, id: BatchId) -> bool {
+        let Some(batch) = self.inflight.remove(&id) else {
+            return false;
+        };
+        if !batch.is_empty() {
+            if let Err(e) = self.compact() {
+                error!(
+                    "Failed to compact transaction log {:?}, keep batch {} in-flight: {}",
+                    self.path, id, e
+                );
+                self.inflight.insert(id, batch);
+                return false;
+            }
+        }
+        self.forget(&batch);
+        true
+    }
+
+    async fn abort_batch(&mut self, id: BatchId) -> bool {
+        match self.inflight.remove(&id) {
+            Some(batch) => {
+                self.transactions.splice(..0, batch);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn random_tx(amount: u64) -> L2Transaction {
+        L2Transaction {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount,
+            calldata: vec![amount as u8; amount as usize],
+        }
+    }
+
+    #[tokio::test]
+    async fn persistent_stream_survives_restart() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pool.log");
+        let txs = (0..5).map(random_tx).collect::<Vec<_>>();
+
+        let mut stream = PersistentStream::new(&path)?;
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await?;
+        }
+        drop(stream);
+
+        let mut stream = PersistentStream::new(&path)?;
+        assert_eq!(stream.transactions, txs);
+        assert_eq!(
+            stream.peek_batch(SimpleBatchSettings {
+                max_size: 2,
+                ..Default::default()
+            }),
+            txs[..2].iter().collect::<Vec<_>>()
+        );
+
+        let batch = stream
+            .next_batch(SimpleBatchSettings {
+                max_size: 2,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch, txs[..2]);
+        drop(stream);
+
+        let stream = PersistentStream::new(&path)?;
+        assert_eq!(stream.transactions, txs[2..]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn duplicate_insert_is_rejected() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pool.log");
+        let tx = random_tx(1);
+
+        let mut stream = PersistentStream::new(&path)?;
+        stream.insert(tx.clone()).await?;
+        assert!(stream.insert(tx.clone()).await.is_err());
+        drop(stream);
+
+        // the ids are restored from the log
+        let mut stream = PersistentStream::new(&path)?;
+        assert!(stream.contains(&tx.id()));
+        assert!(stream.insert(tx.clone()).await.is_err());
+        assert_eq!(stream.len(), 1);
+
+        stream.next_batch(Default::default()).await;
+        assert!(!stream.contains(&tx.id()));
+        stream.insert(tx).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn inflight_batch_survives_restart_until_finalized() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pool.log");
+        let txs = (0..4).map(random_tx).collect::<Vec<_>>();
+
+        let mut stream = PersistentStream::new(&path)?;
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await?;
+        }
+        let (_, batch) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 2,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch, txs[..2]);
+        drop(stream);
+
+        let mut stream = PersistentStream::new(&path)?;
+        assert_eq!(stream.transactions, txs);
+        let (id, _) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 2,
+                ..Default::default()
+            })
+            .await;
+        assert!(stream.finalize_batch(id).await);
+        drop(stream);
+
+        let mut stream = PersistentStream::new(&path)?;
+        assert_eq!(stream.transactions, txs[2..]);
+        let (id, _) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 1,
+                ..Default::default()
+            })
+            .await;
+        assert!(stream.abort_batch(id).await);
+        assert!(!stream.finalize_batch(id).await);
+        assert_eq!(stream.transactions, txs[2..]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn batches_stay_in_the_pool_when_compaction_fails() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pool.log");
+        let txs = (0..3).map(random_tx).collect::<Vec<_>>();
+        let settings = || SimpleBatchSettings {
+            max_size: 2,
+            ..Default::default()
+        };
+
+        let mut stream = PersistentStream::new(&path)?;
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await?;
+        }
+        // a directory in place of the rewritten log makes the compaction fail
+        let blocker = path.with_extension("compact");
+        fs::create_dir(&blocker)?;
+        assert!(stream.next_batch(settings()).await.is_empty());
+        assert_eq!(stream.transactions, txs);
+        assert!(stream.contains(&txs[0].id()));
+
+        let (id, batch) = stream.take_batch_inflight(settings()).await;
+        assert_eq!(batch, txs[..2]);
+        assert!(!stream.finalize_batch(id).await);
+        assert!(stream.contains(&txs[0].id()));
+
+        fs::remove_dir(&blocker)?;
+        assert!(stream.finalize_batch(id).await);
+        assert!(!stream.contains(&txs[0].id()));
+        drop(stream);
+
+        let stream = PersistentStream::new(&path)?;
+        assert_eq!(stream.transactions, txs[2..]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn oversized_transactions_are_dropped_from_the_log() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pool.log");
+        let txs = [20, 1, 2].into_iter().map(random_tx).collect::<Vec<_>>();
+        // the first transaction exceeds the limit on its own
+        let settings = || SimpleBatchSettings {
+            max_size: 1,
+            max_bytes: txs[2].encoded_len(),
+        };
+
+        let mut stream = PersistentStream::new(&path)?;
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await?;
+        }
+        assert_eq!(stream.peek_batch(settings()), vec![&txs[1]]);
+        assert_eq!(stream.next_batch(settings()).await, txs[1..2]);
+        assert!(!stream.contains(&txs[0].id()));
+        drop(stream);
+
+        let stream = PersistentStream::new(&path)?;
+        assert_eq!(stream.transactions, txs[2..]);
+        Ok(())
+    }
+}
@@ -3,8 +3,11 @@ use igloo_interface::{
     l1::PayloadAttribute,
     l2::{executor::Init, Entry, Producer},
 };
-use solana_sdk::{pubkey::Pubkey, transaction::VersionedTransaction};
-use solana_svm::transaction_processor::TransactionBatchProcessor;
+use solana_sdk::{account::AccountSharedData, pubkey::Pubkey, transaction::VersionedTransaction};
+use solana_svm::{
+    transaction_processing_callback::TransactionProcessingCallback,
+    transaction_processor::TransactionBatchProcessor,
+};
 use std::{
     path::Path,
     sync::{Arc, RwLock},
@@ -19,7 +22,7 @@ use svm_executor::{
 use crate::l1::attribute::PayloadAttributeImpl;
 
 use super::{
-    block::{BlockPayloadImpl, SimpleEntry},
+    block::{block_hash, BlockPayloadImpl, SimpleEntry},
     head::L2HeadImpl,
     ledger::SharedLedger,
     tx::L2Transaction,
@@ -43,15 +46,7 @@ impl Producer for SvmProducer {
     type Error = anyhow::Error;
 
     async fn produce(&self, attribute: Self::Attribute) -> anyhow::Result<Self::BlockPayload> {
-        let new_height = { self.ledger.read().await.latest_height() + 1 };
-        let block = BlockPayloadImpl {
-            head: L2HeadImpl {
-                hash: Default::default(),
-                height: new_height,
-                timestamp: chrono::Utc::now().timestamp() as u64,
-            },
-            entries: self.process_txs(attribute).await?,
-        };
+        let (block, _) = self.produce_with_limit(attribute, 0, u64::MAX).await?;
         Ok(block)
     }
 }
@@ -72,13 +67,70 @@ impl SvmProducer {
         })
     }
 
-    async fn process_txs(&self, attribute: PayloadAttributeImpl) -> Result<Vec<SimpleEntry>> {
+    /// Same as `produce`, but stops including transactions once the compute units they
+    /// consume would exceed `compute_limit`. The transaction crossing the limit and all
+    /// following ones are returned in order, to be included in a later block. The first
+    /// `deposits` transactions are derived from L1 and always included, their units still
+    /// count toward the limit.
+    pub async fn produce_with_limit(
+        &self,
+        attribute: PayloadAttributeImpl,
+        deposits: usize,
+        compute_limit: u64,
+    ) -> Result<(BlockPayloadImpl, Vec<L2Transaction>)> {
+        let (new_height, parent) = {
+            let ledger = self.ledger.read().await;
+            (ledger.latest_height() + 1, ledger.latest_hash())
+        };
+        let (entries, deferred) = self
+            .process_txs(attribute.clone(), deposits, compute_limit)
+            .await?;
+        // deferred transactions are a suffix of the payload and not part of the block
+        let included = attribute.transactions.len() - deferred.len();
+        let included = PayloadAttributeImpl {
+            transactions: Arc::new(attribute.transactions[..included].to_vec()),
+            ..attribute
+        };
+        let block = BlockPayloadImpl {
+            head: L2HeadImpl {
+                hash: block_hash(parent, &included),
+                height: new_height,
+                timestamp: chrono::Utc::now().timestamp() as u64,
+            },
+            entries,
+        };
+        Ok((block, deferred))
+    }
+
+    async fn process_txs(
+        &self,
+        attribute: PayloadAttributeImpl,
+        deposits: usize,
+        compute_limit: u64,
+    ) -> Result<(Vec<SimpleEntry>, Vec<L2Transaction>)> {
         // TODO: increase blockheight after processing
         let mut result = vec![];
+        let mut deferred = vec![];
+        let mut consumed_units = 0u64;
 
         let mut txs = vec![];
-        for tx in attribute.transactions().iter() {
-            txs.push(self.process_single_tx(tx).await?);
+        for (index, tx) in attribute.transactions().iter().enumerate() {
+            if !deferred.is_empty() {
+                deferred.push(tx.clone());
+                continue;
+            }
+            // builds are not committed to the bank, so a transaction crossing the limit
+            // can be dropped after execution
+            let (processed, units) = self.process_single_tx(tx).await?;
+            match consumed_units.checked_add(units) {
+                Some(total) if total <= compute_limit => consumed_units = total,
+                total if index < deposits => consumed_units = total.unwrap_or(u64::MAX),
+                _ => {
+                    deferred.push(tx.clone());
+                    continue;
+                }
+            }
+            txs.push(processed);
 
             if txs.len() >= self.txs_per_entry {
                 result.push(SimpleEntry::new(txs));
@@ -90,17 +142,22 @@ impl SvmProducer {
         }
 
         debug!(
-            "{} txs total, {} entries {} txs processed",
+            "{} txs total, {} entries {} txs processed, {} txs deferred",
             attribute.transactions().len(),
             result.len(),
-            result.iter().map(|e| e.tx_count()).sum::<usize>()
+            result.iter().map(|e| e.tx_count()).sum::<usize>(),
+            deferred.len()
         );
 
-        Ok(result)
+        Ok((result, deferred))
     }
 
     // TODO: process batch transactions
-    async fn process_single_tx(&self, tx: &L2Transaction) -> Result<VersionedTransaction> {
+    /// Executes `tx` and returns it along with the compute units it consumed.
+    pub(crate) async fn process_single_tx(
+        &self,
+        tx: &L2Transaction,
+    ) -> Result<(VersionedTransaction, u64)> {
         const INIT_LAMPORTS: u64 = 900000;
         let mut builder = SimpleBuilder::<BankWrapper>::init(&Default::default())?;
         let path = self.get_program_path();
@@ -116,23 +173,30 @@ impl SvmProducer {
             .calldata(tx.amount.to_be_bytes().to_vec())
             .build_ex()?;
 
-        if result
+        let details = result
             .execution_results
             .first()
             .ok_or(anyhow::anyhow!("no result"))?
             .details()
-            .ok_or(anyhow::anyhow!("no details"))?
-            .status
-            .is_err()
-        {
+            .ok_or(anyhow::anyhow!("no details"))?;
+        if details.status.is_err() {
             // simulate failed tx handler
             return Err(anyhow::anyhow!("tx failed"));
         }
 
-        Ok(txs)
+        Ok((txs, details.executed_units))
+    }
+
+    pub fn account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.bank.get_account_shared_data(pubkey)
     }
 
     fn get_program_path(&self) -> String {
-        "svm/executor/tests/simple_transfer_program.so".to_string()
+        // relative to the manifest, so the program is found from any working directory
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../svm/executor/tests/simple_transfer_program.so"
+        )
+        .to_string()
     }
 }
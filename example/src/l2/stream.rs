@@ -1,41 +1,340 @@
-use igloo_interface::l2::stream::{BatchSettings, TransactionStream};
-use std::sync::Arc;
+use igloo_interface::l2::stream::{
+    batch_len, is_oversized, BatchId, BatchSettings, TransactionStream,
+};
+use solana_sdk::{clock::UnixTimestamp, hash::Hash};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
 use tokio::sync::RwLock;
 
+use super::{
+    tx::L2Transaction,
+    validation::{DefaultValidator, TransactionValidator, ValidationError},
+};
+
 pub type SharedStream = Arc<RwLock<TransactionStreamImpl>>;
 
+pub const DEFAULT_POOL_CAPACITY: usize = 100_000;
+
+type Prioritizer = Box<dyn Fn(&L2Transaction) -> u64 + Send + Sync>;
+
 pub struct SimpleBatchSettings {
     pub max_size: usize,
+    /// Unbounded by default.
+    pub max_bytes: usize,
 }
 
 impl Default for SimpleBatchSettings {
     fn default() -> Self {
-        Self { max_size: 1024 }
+        Self {
+            max_size: 1024,
+            max_bytes: usize::MAX,
+        }
     }
 }
 
-#[derive(Default)]
+/// Result of an accepted `insert`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertOutcome {
+    /// The transaction is pending at the given position of the pool.
+    Inserted { position: usize },
+}
+
+/// Reason an `insert` was rejected.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum InsertError {
+    #[error("transaction pool is full, capacity: {0}")]
+    PoolFull(usize),
+
+    #[error("transaction already pending in the pool")]
+    Duplicate,
+
+    #[error(transparent)]
+    Invalid(#[from] ValidationError),
+}
+
 pub struct TransactionStreamImpl {
-    pub transactions: Vec<super::tx::L2Transaction>,
+    pub transactions: Vec<L2Transaction>,
+    inflight: BTreeMap<BatchId, Vec<L2Transaction>>,
+    /// Ids of the pending and in-flight transactions.
+    ids: HashSet<Hash>,
+    /// Deadlines of the pending and in-flight transactions inserted with one.
+    deadlines: HashMap<Hash, UnixTimestamp>,
+    next_batch_id: BatchId,
+    capacity: usize,
+    log_rejections: bool,
+    validator: Box<dyn TransactionValidator>,
+    prioritizer: Option<Prioritizer>,
+}
+
+impl Default for TransactionStreamImpl {
+    fn default() -> Self {
+        Self::new(DEFAULT_POOL_CAPACITY)
+    }
+}
+
+impl TransactionStreamImpl {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            transactions: Default::default(),
+            inflight: Default::default(),
+            ids: Default::default(),
+            deadlines: Default::default(),
+            next_batch_id: 0,
+            capacity,
+            log_rejections: false,
+            validator: Box::new(DefaultValidator::default()),
+            prioritizer: None,
+        }
+    }
+
+    /// Replaces the rules checked by `validate`.
+    pub fn validator(mut self, validator: Box<dyn TransactionValidator>) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Checks `tx` against the validation rules, `insert` rejects transactions failing it.
+    pub fn validate(&self, tx: &L2Transaction) -> Result<(), ValidationError> {
+        self.validator.validate(tx)
+    }
+
+    /// Computes the `priority` of transactions with `prioritizer`, without one batches are
+    /// taken in insertion order.
+    pub fn prioritizer(
+        mut self,
+        prioritizer: impl Fn(&L2Transaction) -> u64 + Send + Sync + 'static,
+    ) -> Self {
+        self.prioritizer = Some(Box::new(prioritizer));
+        self
+    }
+
+    /// Emits a trace log with the reason of every rejected insertion.
+    pub fn log_rejections(mut self, enable: bool) -> Self {
+        self.log_rejections = enable;
+        self
+    }
+
+    /// Whether the pending and in-flight transactions reach the capacity, in-flight ones
+    /// count since an aborted batch is requeued.
+    pub fn is_full(&self) -> bool {
+        self.transactions.len() + self.inflight_len() >= self.capacity
+    }
+
+    /// Number of transactions in batches neither finalized nor aborted yet.
+    pub fn inflight_len(&self) -> usize {
+        self.inflight.values().map(Vec::len).sum()
+    }
+
+    /// Whether the transaction with the given `L2Transaction::id` is pending or in-flight.
+    pub fn contains(&self, id: &Hash) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Same as `insert`, but reports where the transaction was placed.
+    pub fn try_insert(&mut self, tx: L2Transaction) -> Result<InsertOutcome, InsertError> {
+        match self.check_insert(&tx) {
+            Ok(()) => {
+                // if `TxIn` and `TxOut` are not the same type, we should convert here
+                self.ids.insert(tx.id());
+                self.transactions.push(tx);
+                Ok(InsertOutcome::Inserted {
+                    position: self.transactions.len() - 1,
+                })
+            }
+            Err(e) => {
+                if self.log_rejections {
+                    trace!("Reject transaction {:?}: {}", tx, e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Same as `try_insert`, but the transaction is dropped instead of batched once
+    /// `deadline` has passed, e.g. because the blockhash it references becomes stale.
+    pub fn try_insert_with_deadline(
+        &mut self,
+        tx: L2Transaction,
+        deadline: UnixTimestamp,
+    ) -> Result<InsertOutcome, InsertError> {
+        let id = tx.id();
+        let outcome = self.try_insert(tx)?;
+        self.deadlines.insert(id, deadline);
+        Ok(outcome)
+    }
+
+    /// Drops the pending transactions whose deadline is before `now`, returns how many
+    /// were dropped. In-flight transactions are left to their batch.
+    pub fn prune_expired(&mut self, now: UnixTimestamp) -> usize {
+        let (expired, pending) = self
+            .transactions
+            .drain(..)
+            .partition::<Vec<_>, _>(|tx| is_expired(&self.deadlines, tx, now));
+        self.transactions = pending;
+        if self.log_rejections {
+            for tx in expired.iter() {
+                trace!("Drop expired transaction {:?}", tx);
+            }
+        }
+        self.forget(&expired);
+        expired.len()
+    }
+
+    /// Drops the pending transactions exceeding `max_bytes` of `settings` on their own and
+    /// returns them. They never fit into a batch, so taking a batch drops them as well.
+    pub fn prune_oversized(&mut self, settings: &SimpleBatchSettings) -> Vec<L2Transaction> {
+        let (oversized, pending) = self
+            .transactions
+            .drain(..)
+            .partition::<Vec<_>, _>(|tx| is_oversized(tx, settings));
+        self.transactions = pending;
+        for tx in oversized.iter() {
+            warn!(
+                "Drop transaction {:?}, it exceeds the batch limit of {} bytes",
+                tx, settings.max_bytes
+            );
+        }
+        self.forget(&oversized);
+        oversized
+    }
+
+    /// Indices of the pending transactions the next batch consists of, in batch order.
+    fn select_batch(&self, settings: &SimpleBatchSettings, now: UnixTimestamp) -> Vec<usize> {
+        let mut selected = (0..self.transactions.len())
+            .filter(|&index| {
+                let tx = &self.transactions[index];
+                !is_expired(&self.deadlines, tx, now) && !is_oversized(tx, settings)
+            })
+            .collect::<Vec<_>>();
+        if self.prioritizer.is_some() {
+            // the sort is stable, so transactions of the same priority keep their order
+            selected.sort_by_key(|&index| Reverse(self.priority(&self.transactions[index])));
+        }
+        let size = batch_len(
+            selected.iter().map(|&index| &self.transactions[index]),
+            settings,
+        );
+        selected.truncate(size);
+        selected
+    }
+
+    fn drain_batch(&mut self, settings: &SimpleBatchSettings) -> Vec<L2Transaction> {
+        let now = chrono::Utc::now().timestamp();
+        self.prune_expired(now);
+        self.prune_oversized(settings);
+        let selected = self.select_batch(settings, now);
+
+        let mut pending = std::mem::take(&mut self.transactions)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        let batch = selected
+            .into_iter()
+            .map(|index| pending[index].take().expect("transaction selected once"))
+            .collect();
+        self.transactions = pending.into_iter().flatten().collect();
+        batch
+    }
+
+    /// Drops the ids of transactions leaving the pool for good.
+    fn forget(&mut self, batch: &[L2Transaction]) {
+        for tx in batch {
+            let id = tx.id();
+            self.ids.remove(&id);
+            self.deadlines.remove(&id);
+        }
+    }
+
+    fn check_insert(&self, tx: &L2Transaction) -> Result<(), InsertError> {
+        if self.is_full() {
+            return Err(InsertError::PoolFull(self.capacity));
+        }
+        self.validate(tx)?;
+        if self.contains(&tx.id()) {
+            return Err(InsertError::Duplicate);
+        }
+        Ok(())
+    }
+}
+
+fn is_expired(
+    deadlines: &HashMap<Hash, UnixTimestamp>,
+    tx: &L2Transaction,
+    now: UnixTimestamp,
+) -> bool {
+    deadlines
+        .get(&tx.id())
+        .is_some_and(|deadline| *deadline < now)
 }
 
 impl TransactionStream for TransactionStreamImpl {
-    type TxIn = super::tx::L2Transaction;
-    type TxOut = super::tx::L2Transaction;
+    type TxIn = L2Transaction;
+    type TxOut = L2Transaction;
     type Settings = SimpleBatchSettings;
-    type Error = anyhow::Error;
+    type Error = InsertError;
 
-    async fn insert(&mut self, tx: Self::TxIn) -> anyhow::Result<()> {
-        // if `Self::TxIn` and `Self::TxOut` are not the same type, we should convert here
-        self.transactions.push(tx);
-        Ok(())
+    async fn insert(&mut self, tx: Self::TxIn) -> Result<(), Self::Error> {
+        self.try_insert(tx).map(|_| ())
+    }
+
+    fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(self.capacity)
+    }
+
+    fn priority(&self, tx: &Self::TxIn) -> u64 {
+        self.prioritizer
+            .as_ref()
+            .map_or(0, |prioritizer| prioritizer(tx))
     }
 
     async fn next_batch(&mut self, settings: Self::Settings) -> Vec<Self::TxOut> {
-        if self.transactions.len() >= settings.max_size() {
-            self.transactions.drain(..settings.max_size()).collect()
-        } else {
-            self.transactions.drain(..).collect()
+        let batch = self.drain_batch(&settings);
+        self.forget(&batch);
+        batch
+    }
+
+    fn peek_batch(&self, settings: Self::Settings) -> Vec<&Self::TxOut> {
+        self.select_batch(&settings, chrono::Utc::now().timestamp())
+            .into_iter()
+            .map(|index| &self.transactions[index])
+            .collect()
+    }
+
+    async fn take_batch_inflight(
+        &mut self,
+        settings: Self::Settings,
+    ) -> (BatchId, Vec<Self::TxOut>) {
+        let batch = self.drain_batch(&settings);
+        let id = self.next_batch_id;
+        self.next_batch_id += 1;
+        self.inflight.insert(id, batch.clone());
+        (id, batch)
+    }
+
+    async fn finalize_batch(&mut self, id: BatchId) -> bool {
+        match self.inflight.remove(&id) {
+            Some(batch) => {
+                self.forget(&batch);
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn abort_batch(&mut self, id: BatchId) -> bool {
+        match self.inflight.remove(&id) {
+            Some(batch) => {
+                self.transactions.splice(..0, batch);
+                true
+            }
+            None => false,
         }
     }
 }
@@ -44,4 +343,372 @@ impl BatchSettings for SimpleBatchSettings {
     fn max_size(&self) -> usize {
         self.max_size
     }
+
+    fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{packet::PACKET_DATA_SIZE, pubkey::Pubkey};
+
+    fn random_tx() -> L2Transaction {
+        L2Transaction {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount: 1,
+            calldata: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn insert_rejected_at_capacity() {
+        let mut stream = TransactionStreamImpl::new(2);
+        assert_eq!(stream.capacity(), Some(2));
+        assert!(stream.is_empty());
+        stream.insert(random_tx()).await.unwrap();
+        assert!(!stream.is_full());
+        stream.insert(random_tx()).await.unwrap();
+        assert!(stream.is_full());
+        assert_eq!(stream.len(), 2);
+        assert!(stream.insert(random_tx()).await.is_err());
+        assert_eq!(stream.transactions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn insert_accepted_after_next_batch() {
+        let mut stream = TransactionStreamImpl::new(2);
+        stream.insert(random_tx()).await.unwrap();
+        stream.insert(random_tx()).await.unwrap();
+        assert!(stream.insert(random_tx()).await.is_err());
+
+        let (batch, remaining) = stream
+            .next_batch_with_remaining(SimpleBatchSettings {
+                max_size: 1,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch.len(), 1);
+        assert_eq!(remaining, 1);
+        assert!(!stream.is_full());
+        stream.insert(random_tx()).await.unwrap();
+        assert!(stream.is_full());
+    }
+
+    #[tokio::test]
+    async fn insert_reports_rejection_reason() {
+        let mut stream = TransactionStreamImpl::new(2).log_rejections(true);
+        let tx = random_tx();
+        assert_eq!(
+            stream.try_insert(tx.clone()),
+            Ok(InsertOutcome::Inserted { position: 0 })
+        );
+        assert_eq!(stream.try_insert(tx.clone()), Err(InsertError::Duplicate));
+        assert_eq!(stream.insert(tx).await, Err(InsertError::Duplicate));
+
+        assert_eq!(
+            stream.try_insert(random_tx()),
+            Ok(InsertOutcome::Inserted { position: 1 })
+        );
+        assert_eq!(
+            stream.try_insert(random_tx()),
+            Err(InsertError::PoolFull(2))
+        );
+        assert_eq!(stream.transactions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn insert_rejects_invalid_transactions() {
+        let mut stream = TransactionStreamImpl::new(10);
+
+        let mut oversized = random_tx();
+        oversized.calldata = vec![0; PACKET_DATA_SIZE];
+        assert!(matches!(
+            stream.insert(oversized).await,
+            Err(InsertError::Invalid(ValidationError::Oversized { .. }))
+        ));
+
+        let mut self_transfer = random_tx();
+        self_transfer.to = self_transfer.from;
+        assert!(matches!(
+            stream.insert(self_transfer).await,
+            Err(InsertError::Invalid(ValidationError::Malformed(_)))
+        ));
+
+        let mut no_sender = random_tx();
+        no_sender.from = Pubkey::default();
+        assert!(matches!(
+            stream.insert(no_sender).await,
+            Err(InsertError::Invalid(ValidationError::Malformed(_)))
+        ));
+        assert!(stream.transactions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn insert_uses_custom_validator() {
+        struct RejectAll;
+
+        impl TransactionValidator for RejectAll {
+            fn validate(&self, _tx: &L2Transaction) -> Result<(), ValidationError> {
+                Err(ValidationError::Malformed("rejected".to_string()))
+            }
+        }
+
+        let mut stream = TransactionStreamImpl::new(10).validator(Box::new(RejectAll));
+        assert_eq!(
+            stream.insert(random_tx()).await,
+            Err(InsertError::Invalid(ValidationError::Malformed(
+                "rejected".to_string()
+            )))
+        );
+    }
+
+    #[tokio::test]
+    async fn finalize_batch_drops_inflight_transactions() {
+        let mut stream = TransactionStreamImpl::new(10);
+        let txs = (0..3).map(|_| random_tx()).collect::<Vec<_>>();
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await.unwrap();
+        }
+
+        let (id, batch) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 2,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch, txs[..2]);
+        assert_eq!(stream.transactions, txs[2..]);
+        assert_eq!(stream.inflight_len(), 2);
+        assert_eq!(
+            stream.try_insert(txs[0].clone()),
+            Err(InsertError::Duplicate)
+        );
+
+        let (other, batch) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 2,
+                ..Default::default()
+            })
+            .await;
+        assert_ne!(id, other);
+        assert_eq!(batch, txs[2..]);
+
+        assert!(stream.finalize_batch(id).await);
+        assert!(!stream.finalize_batch(id).await);
+        assert!(!stream.abort_batch(id).await);
+        assert_eq!(stream.inflight_len(), 1);
+        assert!(stream.transactions.is_empty());
+        stream.insert(txs[0].clone()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn inflight_transactions_count_toward_capacity() {
+        let mut stream = TransactionStreamImpl::new(2);
+        stream.insert(random_tx()).await.unwrap();
+        stream.insert(random_tx()).await.unwrap();
+
+        let (id, batch) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 1,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch.len(), 1);
+        assert!(stream.is_full());
+        assert_eq!(
+            stream.insert(random_tx()).await,
+            Err(InsertError::PoolFull(2))
+        );
+
+        assert!(stream.finalize_batch(id).await);
+        assert!(!stream.is_full());
+        stream.insert(random_tx()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn abort_batch_requeues_transactions() {
+        let mut stream = TransactionStreamImpl::new(10);
+        let txs = (0..3).map(|_| random_tx()).collect::<Vec<_>>();
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await.unwrap();
+        }
+
+        let (id, batch) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 2,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch, txs[..2]);
+
+        assert!(stream.abort_batch(id).await);
+        assert!(!stream.abort_batch(id).await);
+        assert!(!stream.finalize_batch(id).await);
+        assert_eq!(stream.inflight_len(), 0);
+        assert_eq!(stream.transactions, txs);
+
+        let (_, batch) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 3,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch, txs);
+    }
+
+    #[tokio::test]
+    async fn next_batch_takes_highest_priority_first() {
+        let mut stream = TransactionStreamImpl::new(10).prioritizer(|tx| tx.amount);
+        let txs = [3, 1, 5, 3, 2]
+            .into_iter()
+            .map(|amount| L2Transaction {
+                amount,
+                ..random_tx()
+            })
+            .collect::<Vec<_>>();
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await.unwrap();
+        }
+        assert_eq!(stream.priority(&txs[2]), 5);
+        let peeked = stream
+            .peek_batch(SimpleBatchSettings {
+                max_size: 3,
+                ..Default::default()
+            })
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(stream.transactions, txs);
+
+        let batch = stream
+            .next_batch(SimpleBatchSettings {
+                max_size: 3,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch, [txs[2].clone(), txs[0].clone(), txs[3].clone()]);
+        assert_eq!(batch, peeked);
+        // the remaining transactions keep their insertion order
+        assert_eq!(stream.transactions, [txs[1].clone(), txs[4].clone()]);
+
+        let (_, batch) = stream
+            .take_batch_inflight(SimpleBatchSettings {
+                max_size: 3,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(batch, [txs[4].clone(), txs[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn next_batch_respects_max_bytes() {
+        let mut stream = TransactionStreamImpl::new(10);
+        let txs = [10, 20, 1]
+            .into_iter()
+            .map(|len| L2Transaction {
+                calldata: vec![0; len],
+                ..random_tx()
+            })
+            .collect::<Vec<_>>();
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await.unwrap();
+        }
+
+        // the second transaction does not fit, so the batch stops before it
+        let max_bytes = txs[0].encoded_len() + txs[1].encoded_len() - 1;
+        let settings = || SimpleBatchSettings {
+            max_bytes,
+            ..Default::default()
+        };
+        assert_eq!(stream.next_batch(settings()).await, txs[..1]);
+        assert_eq!(stream.next_batch(settings()).await, txs[1..]);
+        assert!(stream.transactions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn oversized_transactions_do_not_block_the_pool() {
+        let mut stream = TransactionStreamImpl::new(10);
+        let txs = [200, 1, 1]
+            .into_iter()
+            .map(|len| L2Transaction {
+                calldata: vec![0; len],
+                ..random_tx()
+            })
+            .collect::<Vec<_>>();
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await.unwrap();
+        }
+
+        // the first transaction exceeds the limit on its own
+        let settings = || SimpleBatchSettings {
+            max_bytes: txs[1].encoded_len() * 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            stream.peek_batch(settings()),
+            txs[1..].iter().collect::<Vec<_>>()
+        );
+        assert_eq!(stream.next_batch(settings()).await, txs[1..]);
+        assert!(stream.transactions.is_empty());
+        assert!(!stream.contains(&txs[0].id()));
+
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await.unwrap();
+        }
+        assert_eq!(stream.prune_oversized(&settings()), txs[..1]);
+        let (_, batch) = stream.take_batch_inflight(settings()).await;
+        assert_eq!(batch, txs[1..]);
+    }
+
+    #[tokio::test]
+    async fn contains_tracks_pending_and_inflight_transactions() {
+        let mut stream = TransactionStreamImpl::new(10);
+        let txs = (0..2).map(|_| random_tx()).collect::<Vec<_>>();
+        for tx in txs.iter() {
+            stream.insert(tx.clone()).await.unwrap();
+        }
+        assert!(txs.iter().all(|tx| stream.contains(&tx.id())));
+        assert!(!stream.contains(&random_tx().id()));
+
+        let batch = stream
+            .next_batch(SimpleBatchSettings {
+                max_size: 1,
+                ..Default::default()
+            })
+            .await;
+        assert!(!stream.contains(&batch[0].id()));
+        // a batched transaction can be resubmitted
+        stream.insert(batch[0].clone()).await.unwrap();
+
+        let (id, batch) = stream.take_batch_inflight(Default::default()).await;
+        assert_eq!(batch.len(), 2);
+        assert!(batch.iter().all(|tx| stream.contains(&tx.id())));
+        assert!(stream.finalize_batch(id).await);
+        assert!(batch.iter().all(|tx| !stream.contains(&tx.id())));
+    }
+
+    #[tokio::test]
+    async fn expired_transactions_are_dropped() {
+        let mut stream = TransactionStreamImpl::new(10);
+        let txs = (0..4).map(|_| random_tx()).collect::<Vec<_>>();
+        stream.try_insert_with_deadline(txs[0].clone(), 10).unwrap();
+        stream.try_insert_with_deadline(txs[1].clone(), 20).unwrap();
+        stream.insert(txs[2].clone()).await.unwrap();
+
+        assert_eq!(stream.prune_expired(10), 0);
+        assert_eq!(stream.prune_expired(11), 1);
+        assert_eq!(stream.transactions, txs[1..3]);
+        assert!(!stream.contains(&txs[0].id()));
+
+        // a deadline in the past is dropped by the next batch
+        stream.try_insert_with_deadline(txs[3].clone(), 0).unwrap();
+        assert_eq!(stream.peek_batch(Default::default()).len(), 2);
+        let batch = stream.next_batch(Default::default()).await;
+        assert_eq!(batch, txs[1..3]);
+        assert!(stream.transactions.is_empty());
+        assert!(!stream.contains(&txs[3].id()));
+    }
 }
@@ -1,9 +1,13 @@
 use igloo_interface::l2::Transaction;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+    hash::{hash, Hash},
+    pubkey::Pubkey,
+};
 
 use crate::l1::tx::DepositTx;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct L2Transaction {
     pub from: Pubkey,
     pub to: Pubkey,
@@ -30,12 +34,59 @@ impl Transaction for L2Transaction {
     fn calldata(&self) -> &[u8] {
         &self.calldata
     }
+
+    /// Encodes as `from | to | amount (le) | calldata length (le) | calldata`.
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::HEADER_SIZE + self.calldata.len());
+        buf.extend_from_slice(self.from.as_ref());
+        buf.extend_from_slice(self.to.as_ref());
+        buf.extend_from_slice(&self.amount.to_le_bytes());
+        buf.extend_from_slice(&(self.calldata.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&self.calldata);
+        buf
+    }
+
+    fn encoded_len(&self) -> usize {
+        Self::HEADER_SIZE + self.calldata.len()
+    }
+}
+
+impl L2Transaction {
+    const HEADER_SIZE: usize = 32 + 32 + 8 + 4;
+
+    /// Hash of the encoded transaction, identifying it in the transaction pool.
+    pub fn id(&self) -> Hash {
+        hash(&self.encode())
+    }
+
+    /// Decodes a transaction produced by `Transaction::encode`.
+    pub fn decode(buf: &[u8]) -> anyhow::Result<Self> {
+        if buf.len() < Self::HEADER_SIZE {
+            anyhow::bail!("transaction too short: {} bytes", buf.len());
+        }
+        let calldata_len = u32::from_le_bytes(buf[72..76].try_into()?) as usize;
+        if buf.len() != Self::HEADER_SIZE + calldata_len {
+            anyhow::bail!(
+                "transaction length mismatch: expected {}, got {}",
+                Self::HEADER_SIZE + calldata_len,
+                buf.len()
+            );
+        }
+
+        Ok(Self {
+            from: Pubkey::try_from(&buf[0..32])?,
+            to: Pubkey::try_from(&buf[32..64])?,
+            amount: u64::from_le_bytes(buf[64..72].try_into()?),
+            calldata: buf[Self::HEADER_SIZE..].to_vec(),
+        })
+    }
 }
 
 impl TryFrom<DepositTx> for L2Transaction {
     type Error = anyhow::Error;
 
     fn try_from(value: DepositTx) -> Result<Self, Self::Error> {
+        value.validate()?;
         Ok(Self {
             from: value.from,
             to: value.to,
@@ -44,3 +95,23 @@ impl TryFrom<DepositTx> for L2Transaction {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_roundtrip() {
+        let tx = L2Transaction {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount: 42,
+            calldata: vec![1, 2, 3],
+        };
+        let encoded = tx.encode();
+        assert_eq!(encoded.len(), L2Transaction::HEADER_SIZE + 3);
+        assert_eq!(encoded.len(), tx.encoded_len());
+        assert_eq!(L2Transaction::decode(&encoded).unwrap(), tx);
+        assert!(L2Transaction::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use igloo_interface::l2::Transaction;
+use solana_sdk::{packet::PACKET_DATA_SIZE, pubkey::Pubkey};
+
+use super::tx::L2Transaction;
+
+/// Reason a transaction is refused before entering the pool.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("transaction too large: {size} bytes, limit {limit}")]
+    Oversized { size: usize, limit: usize },
+
+    #[error("malformed transaction: {0}")]
+    Malformed(String),
+}
+
+/// Rules a transaction has to satisfy to be inserted into the reference pool. Implement
+/// it to replace the default rules.
+pub trait TransactionValidator: Send + Sync {
+    fn validate(&self, tx: &L2Transaction) -> Result<(), ValidationError>;
+}
+
+pub struct DefaultValidator {
+    /// Maximum size of the encoded transaction.
+    pub max_size: usize,
+}
+
+impl Default for DefaultValidator {
+    fn default() -> Self {
+        Self {
+            max_size: PACKET_DATA_SIZE,
+        }
+    }
+}
+
+impl TransactionValidator for DefaultValidator {
+    fn validate(&self, tx: &L2Transaction) -> Result<(), ValidationError> {
+        let size = tx.encode().len();
+        if size > self.max_size {
+            return Err(ValidationError::Oversized {
+                size,
+                limit: self.max_size,
+            });
+        }
+        if tx.from == Pubkey::default() {
+            return Err(ValidationError::Malformed("missing sender".to_string()));
+        }
+        if tx.from == tx.to {
+            return Err(ValidationError::Malformed(
+                "sender and recipient are the same".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+pub mod derive;
+pub mod l1;
+pub mod l2;
+pub mod mock;
+pub mod runner;
+
+#[macro_use]
+extern crate log;
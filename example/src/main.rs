@@ -1,18 +1,14 @@
 use anyhow::Result;
-use derive::{da::DaDeriveImpl, instant::InstantDeriveImpl};
+use example::{
+    derive::{da::DaDeriveImpl, instant::InstantDeriveImpl},
+    l2::batcher::Batcher,
+    mock::{chain::MockLayer1, stream::TxServer},
+    runner::SimpleRunner,
+};
 use igloo_interface::{l2::Engine, runner::Runner};
-use l2::batcher::Batcher;
-use mock::{chain::MockLayer1, stream::TxServer};
-use runner::SimpleRunner;
 use std::path::Path;
 use tokio::sync::mpsc::channel;
 
-mod derive;
-mod l1;
-mod l2;
-mod mock;
-mod runner;
-
 #[macro_use]
 extern crate log;
 
@@ -37,7 +37,7 @@ impl MockLayer1 {
     fn generate_block(height: u64) -> L1BlockInfoImpl {
         L1BlockInfoImpl {
             l1_head: Self::random_head(height),
-            batch: None,
+            batches: vec![],
             deposit_txs: Self::random_deposit_txs(),
         }
     }
@@ -65,7 +65,7 @@ impl MockLayer1 {
         DepositTx {
             from: from_kp.pubkey(),
             to: to_kp.pubkey(),
-            amount: rand::random::<u64>() % 100,
+            amount: rand::random::<u64>() % 100 + 1,
             calldata: vec![],
         }
     }
@@ -5,22 +5,40 @@ use igloo_interface::{
     derive::{DaDerive, InstantDerive},
     l1::{Epoch, L1BlockInfo, L1Head},
     l2::{EngineApi, L2Head},
-    runner::Runner,
+    runner::{Runner, RunnerEvent},
+};
+use tokio::sync::{
+    broadcast::{self, Receiver},
+    mpsc::Sender,
 };
-use tokio::sync::mpsc::Sender;
 
 use crate::{
     derive::{da::DaDeriveImpl, instant::InstantDeriveImpl},
-    l1::{attribute::PayloadAttributeImpl, head::L1HeadImpl},
+    l1::{attribute::PayloadAttributeImpl, head::L1HeadImpl, tx::DepositTx, L1Height},
     l2::{block::BlockPayloadImpl, engine::SvmEngine, head::L2HeadImpl},
 };
 
+/// Events buffered for every subscriber before the oldest ones are dropped.
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+pub type SimpleRunnerEvent = RunnerEvent<L2HeadImpl, DepositTx>;
+
+type PayloadHook = Box<dyn Fn(&PayloadAttributeImpl) + Send>;
+
 pub struct SimpleRunner {
     engine: SvmEngine,
     instant_derive: Option<InstantDeriveImpl>,
     da_derive: Option<DaDeriveImpl>,
     current_head: Option<L1HeadImpl>,
     sequence_number: u8,
+    processed_height: Option<L1Height>,
+    /// L1 height of the last safely derived attribute, further attributes of it may follow.
+    safe_height: Option<L1Height>,
+    /// Attribute of a later L1 height taken from the DA derive after `advance_n` completed
+    /// enough heights, it is derived first by the next call.
+    next_attribute: Option<PayloadAttributeImpl>,
+    events: broadcast::Sender<SimpleRunnerEvent>,
+    payload_hooks: Vec<PayloadHook>,
 }
 
 impl Runner<SvmEngine, InstantDeriveImpl, DaDeriveImpl> for SimpleRunner {
@@ -38,9 +56,36 @@ impl Runner<SvmEngine, InstantDeriveImpl, DaDeriveImpl> for SimpleRunner {
         &self.engine
     }
 
+    fn subscribe(&self) -> Receiver<SimpleRunnerEvent> {
+        self.events.subscribe()
+    }
+
+    fn on_payload(&mut self, f: impl Fn(&PayloadAttributeImpl) + Send + 'static) {
+        self.payload_hooks.push(Box::new(f));
+    }
+
+    fn safe_head(&self) -> Option<L1Height> {
+        self.processed_height
+    }
+
     async fn advance(&mut self) -> Result<(), Self::Error> {
-        self.advance_safe().await?;
-        self.advance_unsafe().await
+        self.advance_n(usize::MAX).await?;
+        Ok(())
+    }
+
+    async fn advance_n(&mut self, max_blocks: usize) -> Result<usize, Self::Error> {
+        let completed = self.advance_safe(max_blocks).await?;
+        // blocks derived instantly are not safe, they do not complete an L1 height
+        if completed < max_blocks {
+            self.advance_unsafe().await?;
+        }
+        Ok(completed)
+    }
+
+    async fn reorg(&mut self, reset_to: L2HeadImpl) -> Result<(), Self::Error> {
+        self.engine.reorg(reset_to.clone()).await?;
+        self.emit(RunnerEvent::Reorg { head: reset_to });
+        Ok(())
     }
 }
 
@@ -55,33 +100,54 @@ impl SimpleRunner {
             da_derive: None,
             current_head: None,
             sequence_number: 0,
+            processed_height: None,
+            safe_height: None,
+            next_attribute: None,
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            payload_hooks: vec![],
         })
     }
 
-    async fn advance_unsafe(&mut self) -> Result<()> {
+    /// Derives a block from the instant derive, returns whether one was derived.
+    async fn advance_unsafe(&mut self) -> Result<bool> {
         let info = self.instant_derive()?.get_new_block().await?;
+        let mut deposits = vec![];
         let block = if let Some(i) = info {
             self.current_head = Some(i.l1_head().clone());
             self.sequence_number = 0;
 
-            self.engine.produce_block(i.try_into()?).await?
+            deposits = i.deposit_txs.clone();
+            let attribute = i.try_into()?;
+            self.payload_derived(&attribute);
+            self.engine.produce_block(attribute).await?
         } else if let Some(safe_head) = self.current_head.as_ref() {
             self.sequence_number += 1;
 
             let mut attribute: PayloadAttributeImpl = safe_head.clone().try_into()?;
             attribute.sequence_number = self.sequence_number;
+            self.payload_derived(&attribute);
             self.engine.produce_block(attribute).await?
         } else {
-            return Ok(());
+            return Ok(false);
         };
 
-        self.new_block(block).await?;
-        Ok(())
+        let head = self.new_block(block, false).await?;
+        for deposit in deposits {
+            self.emit(RunnerEvent::DepositProcessed {
+                deposit,
+                head: head.clone(),
+            });
+        }
+        Ok(true)
     }
 
     #[allow(clippy::unnecessary_fallible_conversions)]
-    async fn new_block(&mut self, block: BlockPayloadImpl) -> Result<L2HeadImpl> {
+    async fn new_block(&mut self, block: BlockPayloadImpl, safe: bool) -> Result<L2HeadImpl> {
         let new_head = self.engine.new_block(block.try_into()?).await?;
+        self.emit(RunnerEvent::BlockDerived {
+            head: new_head.clone(),
+            safe,
+        });
         info!(
             "new block at: {}, derive from: {}",
             new_head.block_height(),
@@ -93,9 +159,21 @@ impl SimpleRunner {
         Ok(new_head)
     }
 
-    async fn advance_safe(&mut self) -> Result<()> {
+    /// Derives blocks from the DA derive until `max_blocks` L1 heights are fully
+    /// processed, returns how many were. Attributes of already executed blocks are skipped.
+    /// The attributes of an L1 height are derived before those of the next one, so a
+    /// height is fully processed once an attribute of a later height is derived.
+    async fn advance_safe(&mut self, max_blocks: usize) -> Result<usize> {
         trace!("begin of da derive");
-        while let Some(attribute) = self.da_derive()?.next().await {
+        let mut completed = 0;
+        while completed < max_blocks {
+            let attribute = match self.next_attribute.take() {
+                Some(attribute) => attribute,
+                None => match self.da_derive()?.next().await {
+                    Some(attribute) => attribute,
+                    None => break,
+                },
+            };
             if self.has_executed(&attribute) {
                 debug!(
                     "skip executed attribute at L1 height {} sequence number {}",
@@ -105,17 +183,43 @@ impl SimpleRunner {
                 continue;
             }
 
+            let height = attribute.epoch.block_height();
+            if let Some(safe_height) = self.safe_height.filter(|safe| *safe < height) {
+                self.processed(safe_height);
+                completed += 1;
+                if completed == max_blocks {
+                    self.next_attribute = Some(attribute);
+                    break;
+                }
+            }
+            self.safe_height = self.safe_height.max(Some(height));
+            self.payload_derived(&attribute);
             let block = self.engine.produce_block(attribute).await?;
-            self.new_block(block).await?;
+            self.new_block(block, true).await?;
         }
         trace!("end of da derive");
-        Ok(())
+        Ok(completed)
+    }
+
+    fn processed(&mut self, height: L1Height) {
+        self.processed_height = self.processed_height.max(Some(height));
+    }
+
+    fn payload_derived(&self, attribute: &PayloadAttributeImpl) {
+        for hook in self.payload_hooks.iter() {
+            hook(attribute);
+        }
+    }
+
+    fn emit(&self, event: SimpleRunnerEvent) {
+        // sending only fails without subscribers
+        let _ = self.events.send(event);
     }
 
     fn has_executed(&self, attribute: &PayloadAttributeImpl) -> bool {
         if let Some(head) = self.current_head.as_ref() {
-            if head.block_height() > attribute.epoch.block_height() {
-                return true;
+            if head.block_height() != attribute.epoch.block_height() {
+                return head.block_height() > attribute.epoch.block_height();
             }
             return self.sequence_number >= attribute.sequence_number;
         }
@@ -134,3 +238,190 @@ impl SimpleRunner {
             .ok_or(anyhow::anyhow!("DA derive not registered"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::l1::block::L1BlockInfoImpl;
+    use igloo_interface::{l1::PayloadAttribute, l2::Engine};
+    use solana_sdk::pubkey::Pubkey;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn advance_emits_events() -> Result<()> {
+        let base_path = tempfile::tempdir()?.into_path();
+        let (attribute_sender, _attribute_receiver) = mpsc::channel(1);
+        let mut runner = SimpleRunner::new(&base_path, attribute_sender)?;
+        let (instant_sender, instant_receiver) = mpsc::channel(1);
+        runner.register_instant(InstantDeriveImpl::new(instant_receiver));
+        runner.register_da(DaDeriveImpl::default());
+        let mut events = runner.subscribe();
+        assert_eq!(runner.safe_head(), None);
+        let payload_txs = Arc::new(AtomicUsize::new(usize::MAX));
+        let hook_txs = payload_txs.clone();
+        runner.on_payload(move |attribute| {
+            hook_txs.store(attribute.transaction_count(), Ordering::Relaxed);
+        });
+
+        let deposit = DepositTx {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount: 10,
+            calldata: vec![],
+        };
+        instant_sender
+            .send(L1BlockInfoImpl {
+                deposit_txs: vec![deposit.clone()],
+                batches: vec![],
+                l1_head: L1HeadImpl {
+                    hash: [1; 32],
+                    height: 1,
+                    timestamp: 12,
+                },
+            })
+            .await?;
+        assert_eq!(runner.advance_n(0).await?, 0);
+        assert!(events.try_recv().is_err());
+        // the instantly derived block does not complete its L1 height
+        assert_eq!(runner.advance_n(1).await?, 0);
+
+        let RunnerEvent::BlockDerived { head, safe } = events.try_recv()? else {
+            panic!("expected a derived block");
+        };
+        assert!(!safe);
+        let RunnerEvent::DepositProcessed {
+            deposit: processed,
+            head: deposit_head,
+        } = events.try_recv()?
+        else {
+            panic!("expected a processed deposit");
+        };
+        assert_eq!(processed.to, deposit.to);
+        assert_eq!(deposit_head.block_height(), head.block_height());
+        assert!(events.try_recv().is_err());
+        // instantly derived blocks are not safe
+        assert_eq!(runner.safe_head(), None);
+        assert_eq!(payload_txs.load(Ordering::Relaxed), 1);
+        Ok(())
+    }
+
+    fn attribute(height: L1Height, sequence_number: u8) -> Result<PayloadAttributeImpl> {
+        let mut attribute = PayloadAttributeImpl::try_from(L1HeadImpl {
+            hash: [height as u8; 32],
+            height,
+            timestamp: height * 12,
+        })?;
+        attribute.sequence_number = sequence_number;
+        Ok(attribute)
+    }
+
+    #[tokio::test]
+    async fn advance_n_counts_fully_derived_heights() -> Result<()> {
+        let base_path = tempfile::tempdir()?.into_path();
+        let (attribute_sender, _attribute_receiver) = mpsc::channel(1);
+        let mut runner = SimpleRunner::new(&base_path, attribute_sender)?;
+        let (instant_sender, instant_receiver) = mpsc::channel(1);
+        runner.register_instant(InstantDeriveImpl::new(instant_receiver));
+        let da_derive = DaDeriveImpl::default();
+        let (da_sender, da_receiver) = mpsc::channel(1);
+        da_derive.run(da_receiver);
+        runner.register_da(da_derive);
+
+        instant_sender
+            .send(L1BlockInfoImpl {
+                deposit_txs: vec![],
+                batches: vec![],
+                l1_head: L1HeadImpl {
+                    hash: [1; 32],
+                    height: 1,
+                    timestamp: 12,
+                },
+            })
+            .await?;
+        runner.advance_n(1).await?;
+        assert_eq!(runner.safe_head(), None);
+
+        // the DA derive yields the attributes last first, the one executed instantly is
+        // skipped and more attributes of height 2 may follow
+        da_sender
+            .send(vec![attribute(2, 1)?, attribute(2, 0)?, attribute(1, 0)?])
+            .await?;
+        // let the update task move the attributes into the cache
+        tokio::task::yield_now().await;
+        assert_eq!(runner.advance_n(1).await?, 0);
+        assert_eq!(runner.safe_head(), None);
+
+        da_sender
+            .send(vec![attribute(4, 0)?, attribute(3, 0)?])
+            .await?;
+        tokio::task::yield_now().await;
+        assert_eq!(runner.advance_n(1).await?, 1);
+        assert_eq!(runner.safe_head(), Some(2));
+        // the attribute of height 3 left over is derived by the next call
+        assert_eq!(runner.advance_n(usize::MAX).await?, 1);
+        assert_eq!(runner.safe_head(), Some(3));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reorg_drops_later_blocks() -> Result<()> {
+        let base_path = tempfile::tempdir()?.into_path();
+        let (attribute_sender, _attribute_receiver) = mpsc::channel(8);
+        let mut runner = SimpleRunner::new(&base_path, attribute_sender)?;
+        let (instant_sender, instant_receiver) = mpsc::channel(1);
+        runner.register_instant(InstantDeriveImpl::new(instant_receiver));
+        runner.register_da(DaDeriveImpl::default());
+        let mut events = runner.subscribe();
+
+        instant_sender
+            .send(L1BlockInfoImpl {
+                deposit_txs: vec![],
+                batches: vec![],
+                l1_head: L1HeadImpl {
+                    hash: [1; 32],
+                    height: 1,
+                    timestamp: 12,
+                },
+            })
+            .await?;
+        runner.advance_n(1).await?;
+        runner.advance_n(1).await?;
+        let RunnerEvent::BlockDerived { head: first, .. } = events.try_recv()? else {
+            panic!("expected a derived block");
+        };
+        let RunnerEvent::BlockDerived { head: second, .. } = events.try_recv()? else {
+            panic!("expected a derived block");
+        };
+        assert_eq!(second.block_height(), first.block_height() + 1);
+
+        runner.reorg(first.clone()).await?;
+        let RunnerEvent::Reorg { head } = events.try_recv()? else {
+            panic!("expected a reorg");
+        };
+        assert_eq!(head.block_hash(), first.block_hash());
+        assert!(runner
+            .engine
+            .get_head(second.block_height())
+            .await?
+            .is_none());
+        assert!(runner
+            .engine
+            .get_head(first.block_height())
+            .await?
+            .is_some());
+        // the dropped height is produced again
+        runner.advance_n(1).await?;
+        let RunnerEvent::BlockDerived { head, .. } = events.try_recv()? else {
+            panic!("expected a derived block");
+        };
+        assert_eq!(head.block_height(), second.block_height());
+
+        // the dropped block is unknown
+        assert!(runner.reorg(second).await.is_err());
+        Ok(())
+    }
+}
@@ -22,7 +22,22 @@ pub trait PayloadAttribute {
 
     fn transactions(&self) -> Arc<Vec<Self::Transaction>>;
 
+    fn transaction_count(&self) -> usize {
+        self.transactions().len()
+    }
+
     fn epoch_info(&self) -> &Self::Epoch;
 
+    /// Hash and height of the L1 block the payload was derived from.
+    fn l1_origin(
+        &self,
+    ) -> (
+        <Self::Epoch as Epoch>::Hash,
+        <Self::Epoch as Epoch>::BlockHeight,
+    ) {
+        let epoch = self.epoch_info();
+        (epoch.block_hash(), epoch.block_height())
+    }
+
     fn sequence_number(&self) -> Self::SequenceNumber;
 }
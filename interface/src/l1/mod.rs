@@ -38,5 +38,6 @@ pub trait L1BlockInfo<P: PayloadAttribute>: TryInto<P> {
 
     fn deposit_transactions(&self) -> &[Self::DepositTx];
 
-    fn batch_info(&self) -> Option<&Self::Batch>;
+    /// Batches posted in the block, in posting order.
+    fn batches(&self) -> &[Self::Batch];
 }
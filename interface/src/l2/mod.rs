@@ -17,6 +17,14 @@ pub trait Transaction {
     fn to(&self) -> &Self::Address;
     fn amount(&self) -> Self::Amount;
     fn calldata(&self) -> &[u8];
+
+    /// Raw encoded transaction, as posted to the DA layer.
+    fn encode(&self) -> Vec<u8>;
+
+    /// Length of `encode`, implementations should override it to avoid the encoding.
+    fn encoded_len(&self) -> usize {
+        self.encode().len()
+    }
 }
 
 pub trait L2Head {
@@ -61,9 +69,14 @@ pub trait Engine: EngineApi<Self::Block, Self::Head> {
     type Head: L2Head;
     type Block: Block<Head = Self::Head>;
     type BlockHeight: Copy;
+    type Pubkey;
+    type AccountSharedData;
 
     fn stream(&self) -> &Arc<RwLock<Self::TransactionStream>>;
 
+    /// Current L2 state of the account `pubkey`, e.g. after a block has been executed.
+    fn account(&self, pubkey: &Self::Pubkey) -> Option<Self::AccountSharedData>;
+
     async fn get_head(
         &mut self,
         height: Self::BlockHeight,
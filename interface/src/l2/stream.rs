@@ -1,7 +1,38 @@
 use super::Transaction;
 
+/// Identifies a batch taken by `TransactionStream::take_batch_inflight`.
+pub type BatchId = u64;
+
 pub trait BatchSettings {
     fn max_size(&self) -> usize;
+
+    /// Maximum sum of the `encoded_len` of the transactions of a batch, e.g. to fit the
+    /// batch into an L1 blob.
+    fn max_bytes(&self) -> usize {
+        usize::MAX
+    }
+}
+
+/// Number of leading transactions of `txs` fitting into a batch, stopping at the first one
+/// that would exceed either `max_size` or `max_bytes` of `settings`. It is 0 for a leading
+/// transaction that is oversized, see `is_oversized`.
+pub fn batch_len<'a, T: Transaction + 'a>(
+    txs: impl IntoIterator<Item = &'a T>,
+    settings: &impl BatchSettings,
+) -> usize {
+    let mut bytes = 0usize;
+    txs.into_iter()
+        .take(settings.max_size())
+        .take_while(|tx| {
+            bytes = bytes.saturating_add(tx.encoded_len());
+            bytes <= settings.max_bytes()
+        })
+        .count()
+}
+
+/// Whether `tx` alone exceeds `max_bytes` of `settings`, so it never fits into a batch.
+pub fn is_oversized<T: Transaction>(tx: &T, settings: &impl BatchSettings) -> bool {
+    tx.encoded_len() > settings.max_bytes()
 }
 
 pub trait TransactionStream {
@@ -12,5 +43,57 @@ pub trait TransactionStream {
 
     async fn insert(&mut self, tx: Self::TxIn) -> Result<(), Self::Error>;
 
+    /// Number of pending transactions, excluding the in-flight ones.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maximum number of pending and in-flight transactions together, `None` if unbounded.
+    /// Inserting into a full stream fails.
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+
+    /// Priority of `tx`, e.g. derived from its fee. Batches start with the pending
+    /// transactions of the highest priority, transactions of the same priority keep their
+    /// insertion order. All transactions have the same priority by default, so batches
+    /// are taken in insertion order.
+    fn priority(&self, _tx: &Self::TxIn) -> u64 {
+        0
+    }
+
+    /// Takes up to `settings.max_size()` pending transactions, highest `priority` first,
+    /// stopping before the first one exceeding `settings.max_bytes()`, see `batch_len`.
+    /// Oversized transactions, see `is_oversized`, would block the stream forever, so they
+    /// are dropped from it instead.
     async fn next_batch(&mut self, settings: Self::Settings) -> Vec<Self::TxOut>;
+
+    /// The transactions `next_batch` would currently take, without taking them.
+    fn peek_batch(&self, settings: Self::Settings) -> Vec<&Self::TxOut>;
+
+    /// Same as `next_batch`, also returning the number of transactions still pending, so
+    /// a caller can tell whether to take another batch right away.
+    async fn next_batch_with_remaining(
+        &mut self,
+        settings: Self::Settings,
+    ) -> (Vec<Self::TxOut>, usize) {
+        let batch = self.next_batch(settings).await;
+        (batch, self.len())
+    }
+
+    /// Same as `next_batch`, but keeps the returned transactions in-flight until the batch
+    /// is either finalized or aborted, so they are never part of two concurrent batches.
+    async fn take_batch_inflight(
+        &mut self,
+        settings: Self::Settings,
+    ) -> (BatchId, Vec<Self::TxOut>);
+
+    /// Drops the in-flight batch `id` for good, returns false if it is unknown.
+    async fn finalize_batch(&mut self, id: BatchId) -> bool;
+
+    /// Requeues the transactions of the in-flight batch `id` in front of the pending ones,
+    /// returns false if it is unknown.
+    async fn abort_batch(&mut self, id: BatchId) -> bool;
 }
@@ -1,8 +1,32 @@
+use tokio::sync::broadcast::Receiver;
+
 use crate::{
     derive::{DaDerive, InstantDerive},
+    l1::{L1BlockInfo, L1Head},
     l2::Engine,
 };
 
+/// Deposit transaction type of the L1 blocks derived by `ID`.
+pub type DepositOf<ID> =
+    <<ID as InstantDerive>::L1Info as L1BlockInfo<<ID as InstantDerive>::P>>::DepositTx;
+
+/// Block height type of the L1 blocks derived by `ID`.
+pub type L1HeightOf<ID> = <<<ID as InstantDerive>::L1Info as L1BlockInfo<
+    <ID as InstantDerive>::P,
+>>::L1Head as L1Head>::BlockHeight;
+
+/// Events emitted by a runner while advancing the L2 chain, see `Runner::subscribe`.
+#[derive(Debug, Clone)]
+pub enum RunnerEvent<H, D> {
+    /// The L2 block `head` was derived and executed. `safe` blocks are derived from the
+    /// DA layer, the others instantly from L1 blocks.
+    BlockDerived { head: H, safe: bool },
+    /// The L2 chain was reset to `head`.
+    Reorg { head: H },
+    /// The L1 deposit `deposit` was included in the L2 block `head`.
+    DepositProcessed { deposit: D, head: H },
+}
+
 pub trait Runner<E: Engine, ID: InstantDerive, DD: DaDerive> {
     type Error: std::fmt::Display;
 
@@ -12,5 +36,23 @@ pub trait Runner<E: Engine, ID: InstantDerive, DD: DaDerive> {
 
     fn get_engine(&self) -> &E;
 
+    /// Subscribes to the events emitted from now on. A subscriber falling too far behind
+    /// misses the oldest events.
+    fn subscribe(&self) -> Receiver<RunnerEvent<E::Head, DepositOf<ID>>>;
+
+    /// Registers `f` to be called with every payload attribute `advance` derives a block
+    /// from, before the block is produced. Hooks are called in registration order.
+    fn on_payload(&mut self, f: impl Fn(&ID::P) + Send + 'static);
+
+    /// Highest L1 height whose payloads were fully processed by `advance`, `None` before
+    /// the first L1 block was derived.
+    fn safe_head(&self) -> Option<L1HeightOf<ID>>;
+
     async fn advance(&mut self) -> Result<(), Self::Error>;
+
+    /// Same as `advance`, but stops once `max_blocks` L1 blocks are fully processed,
+    /// returning how many were. Payloads left over are derived by the next call.
+    async fn advance_n(&mut self, max_blocks: usize) -> Result<usize, Self::Error>;
+
+    async fn reorg(&mut self, reset_to: E::Head) -> Result<(), Self::Error>;
 }
@@ -2,6 +2,7 @@ use solana_entry::entry::Entry;
 use solana_runtime::{
     bank::{Bank, ExecutedTransactionCounts, NewBankOptions, TotalAccountsStats},
     installed_scheduler_pool::BankWithScheduler,
+    snapshot_archive_info::SnapshotArchiveInfoGetter,
     snapshot_bank_utils,
     snapshot_utils::ArchiveFormat,
 };
@@ -15,7 +16,7 @@ use solana_svm::{
     transaction_processor::LoadAndExecuteSanitizedTransactionsOutput,
     transaction_results::TransactionResults,
 };
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use crate::{
     blockstore::txs::CommitBatch,
@@ -56,6 +57,12 @@ impl RollupStorage {
             Some(slot) => slot,
             None => self.current_height(),
         };
+        self.snapshot_archive(slot)?;
+        Ok(())
+    }
+
+    /// Same as `snapshot`, returning the path of the full snapshot archive of `slot`.
+    pub fn snapshot_archive(&self, slot: Slot) -> Result<PathBuf> {
         let bank = self
             .bank_forks
             .read()
@@ -64,7 +71,7 @@ impl RollupStorage {
             .ok_or(BankError::BankNotExists(slot))?;
 
         let ledger_path = self.config.ledger_path.clone();
-        snapshot_bank_utils::bank_to_full_snapshot_archive(
+        let archive = snapshot_bank_utils::bank_to_full_snapshot_archive(
             &ledger_path,
             &bank,
             None,
@@ -74,7 +81,7 @@ impl RollupStorage {
                 .ok_or(BankError::Common("Unsupported archive format".to_string()))?,
         )
         .map_err(|e| BankError::Common(format!("Failed to snapshot bank: {e}").to_string()))?;
-        Ok(())
+        Ok(archive.path().clone())
     }
 
     pub fn current_bank(&self) -> Arc<Bank> {
@@ -1,7 +1,13 @@
 use process::EntriesProcessor;
 use solana_entry::entry::{next_hash, Entry};
-use solana_ledger::{blockstore_processor, shred::Shred};
-use solana_sdk::{hash::Hash, transaction::VersionedTransaction};
+use solana_ledger::{
+    blockstore::Blockstore,
+    blockstore_options::{AccessType, BlockstoreOptions},
+    blockstore_processor,
+    shred::Shred,
+};
+use solana_sdk::{clock::Slot, hash::Hash, transaction::VersionedTransaction};
+use std::path::Path;
 
 use crate::{error::BankError, Error, Result, RollupStorage};
 
@@ -10,6 +16,33 @@ pub mod txs;
 
 const DEFAULT_NUM_HASHES: u64 = 2;
 
+/// Opens the blockstore of the ledger at `ledger_path` read-only, as a secondary instance,
+/// so it can be inspected while another instance holds the primary access.
+pub fn open_secondary(ledger_path: &Path) -> Result<Blockstore> {
+    Ok(Blockstore::open_with_options(
+        ledger_path,
+        BlockstoreOptions {
+            access_type: AccessType::Secondary,
+            ..BlockstoreOptions::default()
+        },
+    )?)
+}
+
+/// Reads the entries of `slot` back from the blockstore of the ledger at `ledger_path`,
+/// opened with `open_secondary`.
+pub fn read_slot_entries(ledger_path: &Path, slot: Slot) -> Result<Vec<Entry>> {
+    Ok(read_slot_entries_ex(ledger_path, slot)?.0)
+}
+
+/// Same as `read_slot_entries`, also returning whether the slot is full, i.e. whether its
+/// last shred is marked as the last in slot and all shreds before it were received.
+pub fn read_slot_entries_ex(ledger_path: &Path, slot: Slot) -> Result<(Vec<Entry>, bool)> {
+    let blockstore = open_secondary(ledger_path)?;
+    let (entries, _num_shreds, is_full) =
+        blockstore.get_slot_entries_with_shred_info(slot, 0, false)?;
+    Ok((entries, is_full))
+}
+
 impl RollupStorage {
     pub fn get_storage_root(&self) -> u64 {
         self.blockstore.max_root()
@@ -12,16 +12,24 @@ use solana_accounts_db::{
 use solana_ledger::{
     blockstore_options::{BlockstoreRecoveryMode, LedgerColumnOptions},
     leader_schedule::FixedSchedule,
+    shred::MAX_DATA_SHREDS_PER_FEC_BLOCK,
     use_snapshot_archives_at_startup::UseSnapshotArchivesAtStartup,
 };
 use solana_runtime::snapshot_config::SnapshotConfig;
 use solana_sdk::{
-    clock::Slot, genesis_config::GenesisConfig, hash::Hash, pubkey::Pubkey, signature::Keypair,
+    account::AccountSharedData,
+    clock::{Slot, UnixTimestamp},
+    fee_calculator::FeeRateGovernor,
+    genesis_config::{ClusterType, GenesisConfig},
+    hash::Hash,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
     signer::EncodableKey,
 };
 use solana_svm::runtime_config::RuntimeConfig;
 
-use crate::{init::init_config, Result, RollupStorage};
+use crate::{init::init_config, Error, Result, RollupStorage};
 
 pub const MAX_GENESIS_ARCHIVE_UNPACKED_SIZE: u64 = 10 * 1024 * 1024; // 10 MiB
 
@@ -33,6 +41,48 @@ pub struct GlobalConfig {
     pub keypairs: KeypairsConfig,
     pub storage: StorageConfig,
     pub genesis: GenesisConfig,
+    pub genesis_settings: GenesisSettings,
+}
+
+/// Settings used when a default genesis ledger is created from scratch.
+#[derive(Default, Clone)]
+pub struct GenesisSettings {
+    /// Shredder parameters for the genesis slot, `None` keeps the ledger defaults.
+    pub shred: Option<GenesisShredConfig>,
+    /// `creation_time` of the genesis config. Defaults to the Unix epoch rather than the
+    /// current time, so the genesis hash only depends on the keypairs.
+    pub creation_time: UnixTimestamp,
+}
+
+/// Parameters of a default genesis ledger, see `genesis_config_with_params`.
+#[derive(Default, Clone)]
+pub struct GenesisParams {
+    /// `None` disables transaction fees.
+    pub fee_rate_governor: Option<FeeRateGovernor>,
+    /// `None` disables rent.
+    pub rent: Option<Rent>,
+    /// `None` keeps the default of the genesis config. Few ticks per slot make slots, and
+    /// thus tests depending on them, short.
+    pub ticks_per_slot: Option<u64>,
+    /// `None` keeps the default of the genesis config.
+    pub hashes_per_tick: Option<u64>,
+    /// `None` creates a `Development` genesis, which activates all features. Other
+    /// cluster types keep the features inactive, like the genesis of the real cluster.
+    pub cluster_type: Option<ClusterType>,
+    /// Additional accounts existing from slot 0, e.g. pre-funded PDAs or token accounts.
+    /// They must not collide with each other or with the accounts of the genesis.
+    pub accounts: Vec<(Pubkey, AccountSharedData)>,
+}
+
+#[derive(Clone)]
+pub struct GenesisShredConfig {
+    /// Number of data shreds grouped into one erasure batch, the number of coding
+    /// shreds of each batch follows the protocol erasure batch size table.
+    pub data_shreds_per_fec_set: u32,
+    pub merkle_variant: bool,
+    /// Marks the last data shred of slot 0 as the last in slot. Without the marker the
+    /// slot is never complete, which is useful to test the handling of partial slots.
+    pub last_in_slot: bool,
 }
 
 #[derive(Default, Clone)]
@@ -158,6 +208,28 @@ impl Default for HistoryConfig {
     }
 }
 
+impl Default for GenesisShredConfig {
+    fn default() -> Self {
+        Self {
+            data_shreds_per_fec_set: MAX_DATA_SHREDS_PER_FEC_BLOCK,
+            merkle_variant: true,
+            last_in_slot: true,
+        }
+    }
+}
+
+impl GenesisShredConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_DATA_SHREDS_PER_FEC_BLOCK).contains(&self.data_shreds_per_fec_set) {
+            return Err(Error::InitConfigFailed(format!(
+                "data shreds per fec set must be in range [1, {MAX_DATA_SHREDS_PER_FEC_BLOCK}], got {}",
+                self.data_shreds_per_fec_set
+            )));
+        }
+        Ok(())
+    }
+}
+
 impl KeypairsConfig {
     pub fn set_default_path(&mut self, base: &Path) {
         self.validator_key_path
@@ -0,0 +1,53 @@
+use crate::{Error, Result};
+use solana_ledger::blockstore::BLOCKSTORE_DIRECTORY_ROCKS_LEVEL;
+use solana_sdk::genesis_config::{DEFAULT_GENESIS_ARCHIVE, DEFAULT_GENESIS_FILE};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+/// Packages the genesis config of the ledger at `ledger_path` into a `genesis.tar.bz2`
+/// archive in `output_dir`, the format expected by Solana when bootstrapping a node from
+/// a genesis archive. With `include_shreds` the blockstore holding the slot 0 shreds is
+/// packaged as well. Returns the path of the archive.
+pub fn export_genesis_archive(
+    ledger_path: &Path,
+    output_dir: &Path,
+    include_shreds: bool,
+) -> Result<PathBuf> {
+    let genesis_path = ledger_path.join(DEFAULT_GENESIS_FILE);
+    if !genesis_path.exists() {
+        return Err(Error::InitCommon(format!(
+            "genesis config not found: {genesis_path:?}"
+        )));
+    }
+
+    let archive_path = output_dir.join(DEFAULT_GENESIS_ARCHIVE);
+    write_archive(ledger_path, &archive_path, include_shreds).map_err(|e| {
+        Error::InitCommon(format!(
+            "Failed to write genesis archive {archive_path:?}: {e}"
+        ))
+    })?;
+    info!("Genesis archive exported to {:?}", archive_path);
+
+    Ok(archive_path)
+}
+
+fn write_archive(
+    ledger_path: &Path,
+    archive_path: &Path,
+    include_shreds: bool,
+) -> std::io::Result<()> {
+    let encoder =
+        bzip2::write::BzEncoder::new(File::create(archive_path)?, bzip2::Compression::best());
+    let mut archive = tar::Builder::new(encoder);
+    archive.append_path_with_name(ledger_path.join(DEFAULT_GENESIS_FILE), DEFAULT_GENESIS_FILE)?;
+    if include_shreds {
+        archive.append_dir_all(
+            BLOCKSTORE_DIRECTORY_ROCKS_LEVEL,
+            ledger_path.join(BLOCKSTORE_DIRECTORY_ROCKS_LEVEL),
+        )?;
+    }
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
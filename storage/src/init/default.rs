@@ -1,27 +1,61 @@
-use crate::{config::MAX_GENESIS_ARCHIVE_UNPACKED_SIZE, Result};
+use crate::{
+    config::{
+        GenesisParams, GenesisSettings, GenesisShredConfig, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE,
+    },
+    Error, Result,
+};
+use rand::Rng;
+use solana_entry::entry::create_ticks;
 use solana_ledger::{
-    blockstore::create_new_ledger, blockstore_options::LedgerColumnOptions,
+    blockstore::{create_new_ledger, Blockstore},
+    blockstore_options::LedgerColumnOptions,
     genesis_utils::GenesisConfigInfo,
+    shred::{max_ticks_per_n_shreds, ProcessShredsStats, ReedSolomonCache, Shred, Shredder},
+};
+use solana_runtime::genesis_utils::{
+    create_genesis_config_with_leader_ex,
+    create_genesis_config_with_vote_accounts_and_cluster_type, ValidatorVoteKeypairs,
 };
-use solana_runtime::genesis_utils::create_genesis_config_with_leader_ex;
 use solana_sdk::{
     fee_calculator::FeeRateGovernor,
     genesis_config::{ClusterType, GenesisConfig},
+    hash::Hash,
     rent::Rent,
+    shred_version::version_from_hash,
     signature::Keypair,
     signer::Signer,
 };
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
 
 pub const DEFAULT_VALIDATOR_LAMPORTS: u64 = 10_000_000;
 pub const DEFAULT_MINT_LAMPORTS: u64 = 1_000_000_000;
 pub const DEFAULT_STAKE_LAMPORTS: u64 = 50_000_000;
 
-pub(crate) fn default_genesis_config(ledger_path: &Path) -> Result<(GenesisConfigInfo, Keypair)> {
+pub(crate) fn default_genesis_config(
+    ledger_path: &Path,
+    settings: &GenesisSettings,
+) -> Result<(GenesisConfigInfo, Keypair)> {
+    genesis_config_with_params(ledger_path, settings, &GenesisParams::default())
+}
+
+/// Same as `default_genesis_config`, but charges the fees and rent of `params` instead of
+/// none at all.
+pub fn genesis_config_with_params(
+    ledger_path: &Path,
+    settings: &GenesisSettings,
+    params: &GenesisParams,
+) -> Result<(GenesisConfigInfo, Keypair)> {
     let validator_key = Keypair::new();
     let mint_keypair = Keypair::new();
     let voting_keypair = Keypair::new();
-    let genesis_config = create_genesis_config_with_leader_ex(
+    let mut genesis_config = create_genesis_config_with_leader_ex(
         DEFAULT_MINT_LAMPORTS,
         &mint_keypair.pubkey(),
         &validator_key.pubkey(),
@@ -29,12 +63,19 @@ pub(crate) fn default_genesis_config(ledger_path: &Path) -> Result<(GenesisConfi
         &solana_sdk::pubkey::new_rand(),
         DEFAULT_STAKE_LAMPORTS,
         DEFAULT_VALIDATOR_LAMPORTS,
-        FeeRateGovernor::new(0, 0), // most tests can't handle transaction fees
-        Rent::free(),               // most tests don't expect rent
-        ClusterType::Development,
+        // most tests can't handle transaction fees
+        params
+            .fee_rate_governor
+            .clone()
+            .unwrap_or_else(|| FeeRateGovernor::new(0, 0)),
+        // most tests don't expect rent
+        params.rent.unwrap_or_else(Rent::free),
+        params.cluster_type.unwrap_or(ClusterType::Development),
         vec![],
     );
-    init_block_store(ledger_path, &genesis_config)?;
+    genesis_config.creation_time = settings.creation_time;
+    apply_genesis_params(&mut genesis_config, params)?;
+    init_block_store(ledger_path, &genesis_config, settings)?;
 
     Ok((
         GenesisConfigInfo {
@@ -47,7 +88,147 @@ pub(crate) fn default_genesis_config(ledger_path: &Path) -> Result<(GenesisConfi
     ))
 }
 
-fn init_block_store(ledger_path: &Path, genesis_config: &GenesisConfig) -> Result<()> {
+/// A validator seeded by `default_genesis_config_with_validators`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidatorStake {
+    /// Lamports delegated to the validator's vote account.
+    pub stake: u64,
+}
+
+impl Default for ValidatorStake {
+    fn default() -> Self {
+        Self {
+            stake: DEFAULT_STAKE_LAMPORTS,
+        }
+    }
+}
+
+/// Same as `genesis_config_with_params`, but seeds a staked vote account for each of
+/// `validators` instead of a single leader. The first validator is the one described by
+/// the returned `GenesisConfigInfo`, the keypairs of all of them are returned in order.
+/// The stake accounts are created before the rent of `params` applies, so their whole
+/// balance is delegated.
+pub fn default_genesis_config_with_validators(
+    ledger_path: &Path,
+    settings: &GenesisSettings,
+    params: &GenesisParams,
+    validators: &[ValidatorStake],
+) -> Result<(GenesisConfigInfo, Vec<ValidatorVoteKeypairs>)> {
+    if validators.is_empty() {
+        return Err(Error::InitCommon(
+            "genesis needs at least one validator".to_string(),
+        ));
+    }
+
+    let keypairs = validators
+        .iter()
+        .map(|_| ValidatorVoteKeypairs::new_rand())
+        .collect::<Vec<_>>();
+    let mut genesis_config_info = create_genesis_config_with_vote_accounts_and_cluster_type(
+        DEFAULT_MINT_LAMPORTS,
+        &keypairs,
+        validators.iter().map(|validator| validator.stake).collect(),
+        params.cluster_type.unwrap_or(ClusterType::Development),
+    );
+    let genesis_config = &mut genesis_config_info.genesis_config;
+    genesis_config.creation_time = settings.creation_time;
+    // the genesis is created without fees and rent, same defaults as the single leader
+    if let Some(fee_rate_governor) = &params.fee_rate_governor {
+        genesis_config.fee_rate_governor = fee_rate_governor.clone();
+    }
+    if let Some(rent) = params.rent {
+        genesis_config.rent = rent;
+    }
+    apply_genesis_params(genesis_config, params)?;
+    init_block_store(ledger_path, genesis_config, settings)?;
+
+    Ok((genesis_config_info, keypairs))
+}
+
+/// Applies the parameters of `params` not taken by the genesis creation itself.
+fn apply_genesis_params(genesis_config: &mut GenesisConfig, params: &GenesisParams) -> Result<()> {
+    if let Some(ticks_per_slot) = params.ticks_per_slot {
+        genesis_config.ticks_per_slot = ticks_per_slot;
+    }
+    if let Some(hashes_per_tick) = params.hashes_per_tick {
+        genesis_config.poh_config.hashes_per_tick = Some(hashes_per_tick);
+    }
+    for (pubkey, account) in &params.accounts {
+        if genesis_config.accounts.contains_key(pubkey) {
+            return Err(Error::InitCommon(format!(
+                "genesis account {pubkey} already exists"
+            )));
+        }
+        genesis_config.add_account(*pubkey, account.clone());
+    }
+    Ok(())
+}
+
+/// Number of ledgers `default_genesis_configs` creates concurrently: half of the available
+/// cores, as every ledger opens its own RocksDB instance and many file descriptors with it.
+pub fn default_genesis_parallelism() -> usize {
+    thread::available_parallelism()
+        .map_or(1, |cores| cores.get() / 2)
+        .max(1)
+}
+
+/// Creates a fully independent default genesis ledger in each of `ledger_paths`, at most
+/// `default_genesis_parallelism` at a time. Every ledger gets its own keypairs and thus
+/// its own genesis hash.
+pub fn default_genesis_configs(
+    ledger_paths: &[PathBuf],
+    settings: &GenesisSettings,
+) -> Result<Vec<GenesisConfigInfo>> {
+    default_genesis_configs_ex(ledger_paths, settings, default_genesis_parallelism())
+}
+
+/// Same as `default_genesis_configs`, creating at most `max_parallelism` ledgers at a time.
+pub fn default_genesis_configs_ex(
+    ledger_paths: &[PathBuf],
+    settings: &GenesisSettings,
+    max_parallelism: usize,
+) -> Result<Vec<GenesisConfigInfo>> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(ledger_paths.iter().map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|scope| {
+        let handles = (0..max_parallelism.clamp(1, ledger_paths.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = ledger_paths.get(index) else {
+                        break;
+                    };
+                    let result = default_genesis_config(path, settings).map(|(info, _)| info);
+                    results.lock().unwrap()[index] = Some(result);
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle
+                .join()
+                .map_err(|_| Error::InitCommon("genesis creation thread panicked".to_string()))?;
+        }
+        Ok(())
+    })?;
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every ledger is created"))
+        .collect()
+}
+
+fn init_block_store(
+    ledger_path: &Path,
+    genesis_config: &GenesisConfig,
+    settings: &GenesisSettings,
+) -> Result<()> {
+    if let Some(shred) = settings.shred.as_ref() {
+        shred.validate()?;
+    }
+
     let hash = create_new_ledger(
         ledger_path,
         genesis_config,
@@ -56,5 +237,72 @@ fn init_block_store(ledger_path: &Path, genesis_config: &GenesisConfig) -> Resul
     )?;
     info!("Create new ledger done, new genesis hash: {}", hash);
 
+    if let Some(shred) = settings.shred.as_ref() {
+        reshred_genesis_slot(ledger_path, genesis_config, shred)?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the shreds of slot 0 written by `create_new_ledger` with shreds produced
+/// from the same ticks but split into erasure batches of the configured size.
+fn reshred_genesis_slot(
+    ledger_path: &Path,
+    genesis_config: &GenesisConfig,
+    config: &GenesisShredConfig,
+) -> Result<()> {
+    let blockstore = Blockstore::open(ledger_path)?;
+    blockstore.purge_and_compact_slots(0, 0);
+
+    // same ticks as `create_new_ledger`, so the shred version is unchanged
+    let entries = create_ticks(
+        genesis_config.ticks_per_slot,
+        genesis_config.poh_config.hashes_per_tick.unwrap_or(0),
+        genesis_config.hash(),
+    );
+    let version = version_from_hash(&entries.last().ok_or(Error::NoEntries)?.hash);
+
+    let keypair = Keypair::new();
+    let reed_solomon_cache = ReedSolomonCache::default();
+    let mut stats = ProcessShredsStats::default();
+    let mut chained_merkle_root = Hash::new_from_array(rand::thread_rng().gen());
+    let mut next_shred_index = 0u32;
+    let mut next_code_index = 0u32;
+    let mut shreds = vec![];
+
+    let ticks_per_fec_set =
+        max_ticks_per_n_shreds(config.data_shreds_per_fec_set as u64, None).max(1) as usize;
+    let num_fec_sets = entries.len().div_ceil(ticks_per_fec_set);
+    for (i, chunk) in entries.chunks(ticks_per_fec_set).enumerate() {
+        let (data_shreds, coding_shreds) =
+            Shredder::new(0, 0, 0, version).unwrap().entries_to_shreds(
+                &keypair,
+                chunk,
+                config.last_in_slot && i + 1 == num_fec_sets, // is_last_in_slot
+                config.merkle_variant.then_some(chained_merkle_root),
+                next_shred_index,
+                next_code_index,
+                config.merkle_variant,
+                &reed_solomon_cache,
+                &mut stats,
+            );
+        if let Some(shred) = data_shreds.iter().max_by_key(|shred| shred.index()) {
+            if config.merkle_variant {
+                chained_merkle_root = shred.merkle_root().unwrap();
+            }
+            next_shred_index = shred.index() + 1;
+        }
+        if let Some(index) = coding_shreds.iter().map(Shred::index).max() {
+            next_code_index = index + 1;
+        }
+        shreds.extend(data_shreds);
+        shreds.extend(coding_shreds);
+    }
+
+    blockstore.insert_shreds(shreds, None, false)?;
+    // an incomplete slot can not be rooted
+    if config.last_in_slot {
+        blockstore.set_roots(std::iter::once(&0))?;
+    }
     Ok(())
 }
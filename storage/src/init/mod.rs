@@ -27,7 +27,9 @@ use std::{
     sync::{atomic::AtomicBool, Arc, RwLock},
 };
 
+pub mod archive;
 pub mod default;
+pub mod snapshot;
 
 pub const MAX_REPLAY_WAKE_UP_SIGNALS: usize = 1;
 
@@ -148,7 +150,8 @@ fn load_blockstore(
             Ok(genesis_config) => Ok(genesis_config),
             Err(err) => {
                 if cfg.allow_default_genesis {
-                    let (genesis_config, keypair) = default_genesis_config(&cfg.ledger_path)?;
+                    let (genesis_config, keypair) =
+                        default_genesis_config(&cfg.ledger_path, &cfg.genesis_settings)?;
                     cfg.keypairs = KeypairsConfig {
                         validator_keypair: Some(Arc::new(keypair)),
                         mint_keypair: Some(Arc::new(genesis_config.mint_keypair)),
@@ -0,0 +1,23 @@
+use crate::{config::GlobalConfig, Result, RollupStorage};
+use solana_sdk::{clock::Slot, signature::Keypair};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Creates a full snapshot archive of the bank at `slot` of the initialized ledger at
+/// `ledger_path`, e.g. slot 0 right after genesis creation, so a validator can be
+/// bootstrapped from the archive instead of replaying the ledger. The archive is written
+/// to the `full` directory of the ledger, its path is returned.
+pub async fn create_snapshot(ledger_path: &Path, slot: Slot) -> Result<PathBuf> {
+    let mut config = GlobalConfig::new(ledger_path)?;
+    // the identity is only used to produce new banks, which the snapshot doesn't need
+    config.keypairs.validator_keypair = Some(Arc::new(Keypair::new()));
+    let mut store = RollupStorage::new(config)?;
+    let result = store.init().and_then(|_| store.snapshot_archive(slot));
+    store.close().await?;
+    let archive_path = result?;
+    info!("Snapshot archive of slot {slot} created at {archive_path:?}");
+
+    Ok(archive_path)
+}
@@ -15,6 +15,7 @@ mod tests;
 
 use solana_sdk::clock::Slot;
 pub use {
+    blockstore::{open_secondary, read_slot_entries, read_slot_entries_ex},
     error::{Error, Result},
     impls::RollupStorage,
 };
@@ -1,12 +1,16 @@
 use crate::{
     blockstore::txs::CommitBatch,
-    config::GlobalConfig,
+    config::{GlobalConfig, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     execution::TransactionsResultWrapper,
-    init::default::{DEFAULT_MINT_LAMPORTS, DEFAULT_STAKE_LAMPORTS, DEFAULT_VALIDATOR_LAMPORTS},
+    init::default::{
+        default_genesis_configs, default_genesis_configs_ex, default_genesis_parallelism,
+        DEFAULT_MINT_LAMPORTS, DEFAULT_STAKE_LAMPORTS, DEFAULT_VALIDATOR_LAMPORTS,
+    },
     tests::mock::{assert_result_balance, processor::process_transfers_ex},
     RollupStorage,
 };
 use anyhow::Result;
+use solana_accounts_db::hardened_unpack::open_genesis_config;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::{
     signature::Keypair,
@@ -14,6 +18,7 @@ use solana_sdk::{
     system_transaction,
     transaction::{SanitizedTransaction, VersionedTransaction},
 };
+use std::collections::HashSet;
 
 #[tokio::test]
 async fn init_with_all_default_works() -> Result<()> {
@@ -71,6 +76,44 @@ async fn init_with_all_default_works() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn batch_genesis_creation_works() -> Result<()> {
+    let ledger_paths = (0..3)
+        .map(|_| tempfile::tempdir().map(|dir| dir.into_path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let infos = default_genesis_configs(&ledger_paths, &Default::default())?;
+    assert_eq!(infos.len(), ledger_paths.len());
+
+    let hashes = ledger_paths
+        .iter()
+        .map(|path| open_genesis_config(path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE).map(|g| g.hash()))
+        .collect::<std::result::Result<HashSet<_>, _>>()?;
+    assert_eq!(hashes.len(), ledger_paths.len());
+    for info in infos.iter() {
+        assert!(hashes.contains(&info.genesis_config.hash()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn batch_genesis_creation_respects_max_parallelism() -> Result<()> {
+    let ledger_paths = (0..3)
+        .map(|_| tempfile::tempdir().map(|dir| dir.into_path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let infos = default_genesis_configs_ex(&ledger_paths, &Default::default(), 1)?;
+    assert_eq!(infos.len(), ledger_paths.len());
+
+    // results keep the order of the ledger paths with a single worker as well
+    for (path, info) in ledger_paths.iter().zip(infos.iter()) {
+        let genesis = open_genesis_config(path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+        assert_eq!(genesis.hash(), info.genesis_config.hash());
+    }
+    assert!(default_genesis_parallelism() >= 1);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn storage_basic_process_works() -> Result<()> {
     let ledger_path = tempfile::tempdir()?.into_path();
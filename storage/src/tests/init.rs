@@ -0,0 +1,431 @@
+use anyhow::Result;
+use solana_accounts_db::hardened_unpack::{open_genesis_config, unpack_genesis_archive};
+use solana_ledger::blockstore::Blockstore;
+use solana_runtime::snapshot_utils;
+
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    feature_set,
+    fee_calculator::FeeRateGovernor,
+    genesis_config::ClusterType,
+    pubkey::Pubkey,
+    rent::Rent,
+    signer::Signer,
+};
+
+use crate::{
+    blockstore::{open_secondary, read_slot_entries, read_slot_entries_ex},
+    config::{
+        GenesisParams, GenesisSettings, GenesisShredConfig, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE,
+    },
+    init::{
+        archive::export_genesis_archive,
+        default::{
+            default_genesis_config, default_genesis_config_with_validators,
+            genesis_config_with_params, ValidatorStake,
+        },
+        snapshot::create_snapshot,
+    },
+};
+
+#[test]
+fn genesis_with_custom_shred_config_works() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let settings = GenesisSettings {
+        shred: Some(GenesisShredConfig {
+            data_shreds_per_fec_set: 1,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    default_genesis_config(&ledger_path, &settings)?;
+
+    let blockstore = Blockstore::open(&ledger_path)?;
+    assert!(blockstore.is_full(0));
+    assert!(blockstore.is_root(0));
+    let data_shreds = blockstore.get_data_shreds_for_slot(0, 0)?;
+    let coding_shreds = blockstore.get_coding_shreds_for_slot(0, 0)?;
+    assert!(!data_shreds.is_empty());
+    // a single data shred per erasure batch gets more coding shreds than data shreds
+    assert!(coding_shreds.len() > data_shreds.len());
+
+    Ok(())
+}
+
+#[test]
+fn genesis_without_last_in_slot_is_incomplete() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let settings = GenesisSettings {
+        shred: Some(GenesisShredConfig {
+            last_in_slot: false,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    default_genesis_config(&ledger_path, &settings)?;
+
+    let (_entries, is_full) = read_slot_entries_ex(&ledger_path, 0)?;
+    assert!(!is_full);
+    let blockstore = Blockstore::open(&ledger_path)?;
+    assert!(!blockstore.is_full(0));
+    let meta = blockstore.meta(0)?.expect("slot 0 has shreds");
+    assert!(meta.received > 0);
+    assert_eq!(meta.last_index, None);
+
+    Ok(())
+}
+
+#[test]
+fn genesis_creation_time_is_deterministic() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let (genesis, _) = default_genesis_config(&ledger_path, &Default::default())?;
+    assert_eq!(genesis.genesis_config.creation_time, 0);
+
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let settings = GenesisSettings {
+        creation_time: 1_700_000_000,
+        ..Default::default()
+    };
+    let (genesis, _) = default_genesis_config(&ledger_path, &settings)?;
+    assert_eq!(genesis.genesis_config.creation_time, 1_700_000_000);
+    let loaded = open_genesis_config(&ledger_path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+    assert_eq!(loaded.hash(), genesis.genesis_config.hash());
+
+    Ok(())
+}
+
+#[test]
+fn genesis_with_invalid_shred_config_fails() -> Result<()> {
+    for data_shreds_per_fec_set in [0, 33] {
+        let ledger_path = tempfile::tempdir()?.into_path();
+        let settings = GenesisSettings {
+            shred: Some(GenesisShredConfig {
+                data_shreds_per_fec_set,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(default_genesis_config(&ledger_path, &settings).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn exported_genesis_archive_can_be_loaded() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let (genesis, _) = default_genesis_config(&ledger_path, &Default::default())?;
+
+    for include_shreds in [false, true] {
+        let output_dir = tempfile::tempdir()?.into_path();
+        let archive_path = export_genesis_archive(&ledger_path, &output_dir, include_shreds)?;
+        assert!(archive_path.ends_with("genesis.tar.bz2"));
+
+        let unpacked = tempfile::tempdir()?.into_path();
+        unpack_genesis_archive(&archive_path, &unpacked, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+        let loaded = open_genesis_config(&unpacked, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+        assert_eq!(loaded.hash(), genesis.genesis_config.hash());
+
+        if include_shreds {
+            let blockstore = Blockstore::open(&unpacked)?;
+            assert!(blockstore.is_full(0));
+        } else {
+            assert!(!unpacked.join("rocksdb").exists());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn export_genesis_archive_fails_without_genesis() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let output_dir = tempfile::tempdir()?.into_path();
+    assert!(export_genesis_archive(&ledger_path, &output_dir, false).is_err());
+    Ok(())
+}
+
+#[test]
+fn genesis_with_params_charges_fees_and_rent() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let (genesis, _) = default_genesis_config(&ledger_path, &Default::default())?;
+    assert_eq!(genesis.genesis_config.rent, Rent::free());
+    assert_eq!(
+        genesis
+            .genesis_config
+            .fee_rate_governor
+            .target_lamports_per_signature,
+        0
+    );
+
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let params = GenesisParams {
+        fee_rate_governor: Some(FeeRateGovernor::new(5_000, 0)),
+        rent: Some(Rent::default()),
+        ..Default::default()
+    };
+    let (genesis, _) = genesis_config_with_params(&ledger_path, &Default::default(), &params)?;
+    assert_eq!(genesis.genesis_config.rent, Rent::default());
+    assert_eq!(
+        genesis
+            .genesis_config
+            .fee_rate_governor
+            .target_lamports_per_signature,
+        5_000
+    );
+    let loaded = open_genesis_config(&ledger_path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+    assert_eq!(loaded.hash(), genesis.genesis_config.hash());
+
+    Ok(())
+}
+
+#[test]
+fn genesis_with_multiple_validators_works() -> Result<()> {
+    let validators = [1_000_000, 2_000_000, 3_000_000].map(|stake| ValidatorStake { stake });
+    for shred in [None, Some(GenesisShredConfig::default())] {
+        let ledger_path = tempfile::tempdir()?.into_path();
+        let settings = GenesisSettings {
+            shred,
+            ..Default::default()
+        };
+        let (genesis, keypairs) = default_genesis_config_with_validators(
+            &ledger_path,
+            &settings,
+            &Default::default(),
+            &validators,
+        )?;
+        assert_eq!(keypairs.len(), validators.len());
+        assert_eq!(genesis.validator_pubkey, keypairs[0].node_keypair.pubkey());
+        for (keypair, validator) in keypairs.iter().zip(&validators) {
+            let accounts = &genesis.genesis_config.accounts;
+            assert!(accounts.contains_key(&keypair.node_keypair.pubkey()));
+            assert!(accounts.contains_key(&keypair.vote_keypair.pubkey()));
+            let stake_account = &accounts[&keypair.stake_keypair.pubkey()];
+            assert_eq!(stake_account.lamports, validator.stake);
+        }
+
+        let blockstore = Blockstore::open(&ledger_path)?;
+        assert!(blockstore.is_full(0));
+        assert!(blockstore.is_root(0));
+        let loaded = open_genesis_config(&ledger_path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+        assert_eq!(loaded.hash(), genesis.genesis_config.hash());
+    }
+
+    let ledger_path = tempfile::tempdir()?.into_path();
+    assert!(default_genesis_config_with_validators(
+        &ledger_path,
+        &Default::default(),
+        &Default::default(),
+        &[]
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn genesis_with_multiple_validators_applies_params() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let (genesis, _) = default_genesis_config_with_validators(
+        &ledger_path,
+        &Default::default(),
+        &Default::default(),
+        &[ValidatorStake::default(); 2],
+    )?;
+    assert_eq!(genesis.genesis_config.rent, Rent::free());
+    assert_eq!(
+        genesis
+            .genesis_config
+            .fee_rate_governor
+            .target_lamports_per_signature,
+        0
+    );
+    assert_eq!(
+        genesis.genesis_config.cluster_type,
+        ClusterType::Development
+    );
+
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let params = GenesisParams {
+        fee_rate_governor: Some(FeeRateGovernor::new(5_000, 0)),
+        rent: Some(Rent::default()),
+        ticks_per_slot: Some(8),
+        cluster_type: Some(ClusterType::MainnetBeta),
+        ..Default::default()
+    };
+    let (genesis, _) = default_genesis_config_with_validators(
+        &ledger_path,
+        &Default::default(),
+        &params,
+        &[ValidatorStake::default(); 2],
+    )?;
+    assert_eq!(genesis.genesis_config.rent, Rent::default());
+    assert_eq!(
+        genesis
+            .genesis_config
+            .fee_rate_governor
+            .target_lamports_per_signature,
+        5_000
+    );
+    assert_eq!(genesis.genesis_config.ticks_per_slot, 8);
+    assert_eq!(
+        genesis.genesis_config.cluster_type,
+        ClusterType::MainnetBeta
+    );
+    assert!(!genesis
+        .genesis_config
+        .accounts
+        .contains_key(&feature_set::full_inflation::devnet_and_testnet::id()));
+    let loaded = open_genesis_config(&ledger_path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+    assert_eq!(loaded.hash(), genesis.genesis_config.hash());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn snapshot_after_genesis_works() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    default_genesis_config(&ledger_path, &Default::default())?;
+
+    let archive_path = create_snapshot(&ledger_path, 0).await?;
+    assert!(archive_path.exists());
+    assert_eq!(
+        snapshot_utils::get_highest_full_snapshot_archive_slot(ledger_path.join("full")),
+        Some(0)
+    );
+
+    // no bank has been produced at slot 1 yet
+    assert!(create_snapshot(&ledger_path, 1).await.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn genesis_with_short_slots_works() -> Result<()> {
+    let params = GenesisParams {
+        ticks_per_slot: Some(4),
+        hashes_per_tick: Some(8),
+        ..Default::default()
+    };
+    for shred in [None, Some(GenesisShredConfig::default())] {
+        let ledger_path = tempfile::tempdir()?.into_path();
+        let settings = GenesisSettings {
+            shred,
+            ..Default::default()
+        };
+        let (genesis, _) = genesis_config_with_params(&ledger_path, &settings, &params)?;
+        assert_eq!(genesis.genesis_config.ticks_per_slot, 4);
+        assert_eq!(genesis.genesis_config.poh_config.hashes_per_tick, Some(8));
+
+        let entries = read_slot_entries(&ledger_path, 0)?;
+        assert_eq!(entries.len(), 4);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.is_tick() && entry.num_hashes == 8));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn read_slot_entries_works() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let (genesis, _) = default_genesis_config(&ledger_path, &Default::default())?;
+    // the primary instance stays open while the entries are read
+    let _blockstore = Blockstore::open(&ledger_path)?;
+
+    let (entries, is_full) = read_slot_entries_ex(&ledger_path, 0)?;
+    assert!(is_full);
+    assert_eq!(entries.len() as u64, genesis.genesis_config.ticks_per_slot);
+    assert!(entries.iter().all(|entry| entry.is_tick()));
+    assert!(read_slot_entries(&ledger_path, 1)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn genesis_cluster_type_round_trips() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let (genesis, _) = default_genesis_config(&ledger_path, &Default::default())?;
+    assert_eq!(
+        genesis.genesis_config.cluster_type,
+        ClusterType::Development
+    );
+    // development genesis activates all features
+    assert!(genesis
+        .genesis_config
+        .accounts
+        .contains_key(&feature_set::full_inflation::devnet_and_testnet::id()));
+
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let params = GenesisParams {
+        cluster_type: Some(ClusterType::MainnetBeta),
+        ..Default::default()
+    };
+    let (genesis, _) = genesis_config_with_params(&ledger_path, &Default::default(), &params)?;
+    assert_eq!(
+        genesis.genesis_config.cluster_type,
+        ClusterType::MainnetBeta
+    );
+    assert!(!genesis
+        .genesis_config
+        .accounts
+        .contains_key(&feature_set::full_inflation::devnet_and_testnet::id()));
+    let loaded = open_genesis_config(&ledger_path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+    assert_eq!(loaded.cluster_type, ClusterType::MainnetBeta);
+    assert_eq!(loaded.hash(), genesis.genesis_config.hash());
+
+    Ok(())
+}
+
+#[test]
+fn genesis_with_extra_accounts_works() -> Result<()> {
+    let owner = Pubkey::new_unique();
+    let pda = Pubkey::find_program_address(&[b"seed"], &owner).0;
+    let mut account = AccountSharedData::new(1_000, 4, &owner);
+    account.set_data_from_slice(&[1, 2, 3, 4]);
+
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let params = GenesisParams {
+        accounts: vec![(pda, account.clone())],
+        ..Default::default()
+    };
+    genesis_config_with_params(&ledger_path, &Default::default(), &params)?;
+    let loaded = open_genesis_config(&ledger_path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
+    let seeded = &loaded.accounts[&pda];
+    assert_eq!(seeded.lamports, 1_000);
+    assert_eq!(seeded.owner, owner);
+    assert_eq!(seeded.data(), account.data());
+
+    // feature accounts are created by the development genesis itself
+    let feature = feature_set::full_inflation::devnet_and_testnet::id();
+    for accounts in [
+        vec![(feature, account.clone())],
+        vec![(pda, account.clone()), (pda, account)],
+    ] {
+        let ledger_path = tempfile::tempdir()?.into_path();
+        let params = GenesisParams {
+            accounts,
+            ..Default::default()
+        };
+        assert!(genesis_config_with_params(&ledger_path, &Default::default(), &params).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn open_secondary_works_while_primary_is_open() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    default_genesis_config(&ledger_path, &Default::default())?;
+    let primary = Blockstore::open(&ledger_path)?;
+
+    let secondary = open_secondary(&ledger_path)?;
+    assert!(secondary.is_full(0));
+    assert!(secondary.is_root(0));
+    assert_eq!(secondary.max_root(), primary.max_root());
+    // a second primary instance fails on the RocksDB lock
+    assert!(Blockstore::open(&ledger_path).is_err());
+
+    Ok(())
+}
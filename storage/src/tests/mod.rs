@@ -1,3 +1,4 @@
 mod basic;
+mod init;
 pub mod mock;
 mod reorg;
@@ -57,7 +57,7 @@ fn run<
 
     let result = builder.build()?;
 
-    for result in result.execution_results {
+    for result in result.output.execution_results {
         match result {
             TransactionExecutionResult::Executed { details, .. } => {
                 info!(
@@ -0,0 +1,29 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use igloo_interface::l2::bank::{BankInfo, BankOperations};
+use solana_program_runtime::loaded_programs::ForkGraph;
+use solana_sdk::{account::AccountSharedData, clock::Slot, hash::Hash, pubkey::Pubkey};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+use crate::{builder::output::ExecutionSummary, prelude::*};
+
+impl<B, FG> SimpleBuilder<B, FG>
+where
+    B: TransactionProcessingCallback
+        + BankOperations<Pubkey = Pubkey, AccountSharedData = AccountSharedData>
+        + BankInfo<Hash = Hash, Pubkey = Pubkey, Slot = Slot>,
+    FG: ForkGraph,
+{
+    /// Sets the calldata to the borsh serialization of `value`.
+    pub fn calldata_borsh<T: BorshSerialize>(&mut self, value: &T) -> &mut Self {
+        // serializing into a `Vec` only fails if the type's implementation does
+        let calldata = borsh::to_vec(value).expect("Failed to serialize calldata");
+        self.calldata(calldata)
+    }
+}
+
+impl ExecutionSummary {
+    /// Deserializes the return data with borsh, failing if it isn't exactly one `T`.
+    pub fn return_data_borsh<T: BorshDeserialize>(&self) -> Result<T> {
+        Ok(borsh::from_slice(&self.return_data)?)
+    }
+}
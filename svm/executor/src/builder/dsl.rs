@@ -0,0 +1,58 @@
+use std::str::FromStr;
+
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+
+use crate::prelude::*;
+
+/// Parses a compact, comma separated list of account metas like `"Wr:<pubkey>, S:<pubkey>"`.
+///
+/// Each token is `<flags>:<pubkey>`, where flags is a combination of `W` (writable),
+/// `S` (signer) and `r` (readable, which every account is). Accounts without `W` are
+/// read-only.
+pub fn parse_account_metas(dsl: &str) -> Result<Vec<AccountMeta>> {
+    dsl.split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(parse_account_meta)
+        .collect()
+}
+
+/// Formats account metas in the format accepted by [`parse_account_metas`].
+pub fn format_account_metas(metas: &[AccountMeta]) -> String {
+    metas
+        .iter()
+        .map(|meta| {
+            let mut flags = String::new();
+            if meta.is_signer {
+                flags.push('S');
+            }
+            if meta.is_writable {
+                flags.push('W');
+            }
+            flags.push('r');
+            format!("{flags}:{}", meta.pubkey)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn parse_account_meta(token: &str) -> Result<AccountMeta> {
+    let invalid =
+        |reason: &str| Error::BuilderError(format!("Invalid account `{token}`: {reason}"));
+
+    let (flags, pubkey) = token
+        .split_once(':')
+        .ok_or_else(|| invalid("missing `:`"))?;
+    let pubkey = Pubkey::from_str(pubkey.trim()).map_err(|e| invalid(&e.to_string()))?;
+
+    let mut meta = AccountMeta::new_readonly(pubkey, false);
+    for flag in flags.trim().chars() {
+        match flag {
+            'W' => meta.is_writable = true,
+            'S' => meta.is_signer = true,
+            'r' => {}
+            _ => return Err(invalid(&format!("unknown flag `{flag}`"))),
+        }
+    }
+    Ok(meta)
+}
@@ -0,0 +1,37 @@
+use igloo_interface::l2::{
+    bank::{BankInfo, BankOperations},
+    executor::{Config, Init},
+};
+use solana_ledger::genesis_utils::GenesisConfigInfo;
+use solana_program_runtime::loaded_programs::ForkGraph;
+use solana_sdk::{
+    account::AccountSharedData, clock::Slot, hash::Hash, pubkey::Pubkey, signer::Signer,
+};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+use crate::prelude::*;
+
+impl<B, C, FG> SimpleBuilder<B, FG>
+where
+    B: TransactionProcessingCallback
+        + BankOperations<Pubkey = Pubkey, AccountSharedData = AccountSharedData>
+        + BankInfo<Hash = Hash, Pubkey = Pubkey, Slot = Slot>
+        + Init<Config = C>,
+    C: Config,
+    FG: ForkGraph + Default,
+{
+    /// Creates a builder whose bank holds every account of the genesis config, e.g. the
+    /// funded mint and validator accounts. The mint funds the fee payers, see
+    /// `funding_source`, and its keypair can sign transfers to other accounts.
+    pub fn from_genesis(cfg: &C, genesis: &GenesisConfigInfo) -> Result<Self> {
+        let mut builder = Self::init(cfg)?;
+        for (pubkey, account) in genesis.genesis_config.accounts.iter() {
+            builder
+                .bank_mut()
+                .insert_account(*pubkey, AccountSharedData::from(account.clone()))
+                .map_err(|e| Error::BuilderError(e.to_string()))?;
+        }
+        builder.funding_source(Some(genesis.mint_keypair.pubkey()));
+        Ok(builder)
+    }
+}
@@ -1,5 +1,13 @@
+#[cfg(feature = "borsh")]
+pub mod borsh;
 #[cfg(test)]
 mod db_tests;
+pub mod dsl;
+pub mod genesis;
+pub mod output;
+pub mod parallel;
 pub mod simple;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "token")]
+pub mod token;
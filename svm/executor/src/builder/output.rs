@@ -0,0 +1,623 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+    ops::Deref,
+};
+
+use solana_sdk::{
+    account::{accounts_equal, AccountSharedData, ReadableAccount},
+    hash::Hash,
+    pubkey::Pubkey,
+    transaction::TransactionError,
+    transaction_context::TransactionReturnData,
+};
+use solana_svm::{
+    transaction_processor::LoadAndExecuteSanitizedTransactionsOutput,
+    transaction_results::TransactionExecutionResult,
+};
+
+/// Maximum number of data bytes printed by `BuildOutput::debug_report`.
+pub const MAX_REPORT_DATA_LEN: usize = 64;
+
+/// Appended to the log message cut by `SimpleBuilder::max_log_bytes`.
+pub const TRUNCATED_MARKER: &str = "...(truncated)";
+
+/// The output of a `SimpleBuilder` build. It dereferences to the raw processor output
+/// and additionally keeps the bank state captured right before execution.
+pub struct BuildOutput {
+    pub output: LoadAndExecuteSanitizedTransactionsOutput,
+    /// Every writable account of the transaction and its state in the bank before
+    /// execution.
+    pre_accounts: Vec<(Pubkey, Option<AccountSharedData>)>,
+    /// Every account referenced by the transaction, read-only ones included, and whether
+    /// it existed in the bank before execution.
+    referenced_accounts: Vec<(Pubkey, bool)>,
+    fee_payer: FeePayerInfo,
+    logs_truncated: bool,
+    return_data_truncated: bool,
+    intermediate_states: Vec<HashMap<Pubkey, AccountSharedData>>,
+    instruction_units: Vec<u64>,
+    message_hashes: Vec<Hash>,
+}
+
+/// The fee payer of a build and its balance right before execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeePayerInfo {
+    pub fee_payer: Pubkey,
+    pub balance: u64,
+    pub required_fee: u64,
+}
+
+/// Fields of the ProgramData account of a program deployed with the upgradeable loader,
+/// see `SimpleBuilder::program_data_after`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramDataInfo {
+    pub program_id: Pubkey,
+    pub programdata_address: Pubkey,
+    /// Slot of the last deployment or upgrade.
+    pub slot: u64,
+    pub upgrade_authority: Option<Pubkey>,
+    /// Bytes available to the program, including the room left for upgrades.
+    pub program_len: usize,
+}
+
+/// Program cache activity during a build, see `SimpleBuilder::last_build_cache_stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Programs found in the cache.
+    pub hits: u64,
+    /// Programs missing from the cache, which had to be loaded.
+    pub misses: u64,
+    /// Programs compiled again after they had been unloaded from the cache.
+    pub recompiles: u64,
+}
+
+impl CacheStats {
+    /// Activity since the counters were at `earlier`.
+    pub fn since(&self, earlier: &CacheStats) -> CacheStats {
+        CacheStats {
+            hits: self.hits.saturating_sub(earlier.hits),
+            misses: self.misses.saturating_sub(earlier.misses),
+            recompiles: self.recompiles.saturating_sub(earlier.recompiles),
+        }
+    }
+}
+
+/// Explains why a transaction could not be loaded because of its fee payer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeePayerDiagnostic {
+    pub fee_payer: Pubkey,
+    pub balance: u64,
+    pub required_fee: u64,
+    pub error: TransactionError,
+}
+
+impl fmt::Display for FeePayerDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Fee payer {} failed to pay the transaction fee: balance {} lamports, required {} lamports ({})",
+            self.fee_payer, self.balance, self.required_fee, self.error
+        )
+    }
+}
+
+/// Outcome of a single transaction of a build, see `BuildOutput::results`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSummary<'a> {
+    pub index: usize,
+    /// Whether the transaction was loaded and executed, even if it failed.
+    pub executed: bool,
+    pub status: Result<(), TransactionError>,
+    pub executed_units: u64,
+    pub logs: &'a [String],
+    pub return_data: Option<&'a TransactionReturnData>,
+}
+
+impl TransactionSummary<'_> {
+    pub fn is_ok(&self) -> bool {
+        self.status.is_ok()
+    }
+}
+
+/// Owned outcome of the last transaction of a build, see `SimpleBuilder::last_execution`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionSummary {
+    pub result: Result<(), TransactionError>,
+    pub logs: Vec<String>,
+    /// Data set by the program with `sol_set_return_data`, empty if none was set.
+    pub return_data: Vec<u8>,
+    pub compute_units: u64,
+}
+
+impl From<TransactionSummary<'_>> for ExecutionSummary {
+    fn from(summary: TransactionSummary<'_>) -> Self {
+        Self {
+            result: summary.status,
+            logs: summary.logs.to_vec(),
+            return_data: summary
+                .return_data
+                .map(|return_data| return_data.data.clone())
+                .unwrap_or_default(),
+            compute_units: summary.executed_units,
+        }
+    }
+}
+
+impl BuildOutput {
+    pub fn new(
+        output: LoadAndExecuteSanitizedTransactionsOutput,
+        pre_accounts: Vec<(Pubkey, Option<AccountSharedData>)>,
+        fee_payer: FeePayerInfo,
+    ) -> Self {
+        Self {
+            output,
+            pre_accounts,
+            referenced_accounts: vec![],
+            fee_payer,
+            logs_truncated: false,
+            return_data_truncated: false,
+            intermediate_states: vec![],
+            instruction_units: vec![],
+            message_hashes: vec![],
+        }
+    }
+
+    pub(crate) fn set_referenced_accounts(&mut self, accounts: Vec<(Pubkey, bool)>) {
+        self.referenced_accounts = accounts;
+    }
+
+    pub(crate) fn set_message_hashes(&mut self, hashes: Vec<Hash>) {
+        self.message_hashes = hashes;
+    }
+
+    pub(crate) fn set_intermediate_states(
+        &mut self,
+        states: Vec<HashMap<Pubkey, AccountSharedData>>,
+    ) {
+        self.intermediate_states = states;
+    }
+
+    /// Attributes the compute units of the first transaction to its `instructions`
+    /// top-level instructions for `per_instruction_units`. The logs must not be truncated
+    /// yet. Builtins do not log their consumption, the units left after the logged ones
+    /// are split evenly among the builtin instructions invoked, which is exact as long as
+    /// they are of the same builtin. Nothing is recorded without logs.
+    pub(crate) fn record_instruction_units(&mut self, instructions: usize) {
+        let Some(details) = self
+            .output
+            .execution_results
+            .first()
+            .and_then(|result| result.details())
+        else {
+            return;
+        };
+        let logged = top_level_units(details.log_messages.as_deref().unwrap_or_default());
+        if logged.is_empty() || logged.len() > instructions {
+            return;
+        }
+
+        let builtins = logged.iter().filter(|units| units.is_none()).count() as u64;
+        let mut remaining = details
+            .executed_units
+            .saturating_sub(logged.iter().flatten().sum());
+        let mut units = Vec::with_capacity(instructions);
+        let mut builtins_left = builtins;
+        for consumed in logged {
+            units.push(consumed.unwrap_or_else(|| {
+                let share = remaining / builtins_left;
+                remaining -= share;
+                builtins_left -= 1;
+                share
+            }));
+        }
+        // instructions after a failing one are not invoked
+        units.resize(instructions, 0);
+        self.instruction_units = units;
+    }
+
+    /// Bounds the captured logs to `max_log_bytes` bytes in total, the message crossing
+    /// the limit is cut and ends with `TRUNCATED_MARKER`, later messages are dropped.
+    /// Return data is cut to `max_return_data_bytes` bytes.
+    pub(crate) fn truncate(
+        &mut self,
+        max_log_bytes: Option<usize>,
+        max_return_data_bytes: Option<usize>,
+    ) {
+        for result in self.output.execution_results.iter_mut() {
+            let TransactionExecutionResult::Executed { details, .. } = result else {
+                continue;
+            };
+            if let (Some(max), Some(logs)) = (max_log_bytes, details.log_messages.as_mut()) {
+                self.logs_truncated |= truncate_logs(logs, max);
+            }
+            if let (Some(max), Some(return_data)) =
+                (max_return_data_bytes, details.return_data.as_mut())
+            {
+                if return_data.data.len() > max {
+                    return_data.data.truncate(max);
+                    self.return_data_truncated = true;
+                }
+            }
+        }
+    }
+
+    /// Whether logs were cut by `SimpleBuilder::max_log_bytes`.
+    pub fn logs_truncated(&self) -> bool {
+        self.logs_truncated
+    }
+
+    /// Whether return data was cut by `SimpleBuilder::max_return_data_bytes`.
+    pub fn return_data_truncated(&self) -> bool {
+        self.return_data_truncated
+    }
+
+    /// Hash of the message of the transaction, the data its signers sign. For builds of
+    /// multiple transactions it is the hash of the first one, see `message_hashes`.
+    pub fn message_hash(&self) -> Hash {
+        self.message_hashes.first().copied().unwrap_or_default()
+    }
+
+    /// Message hashes of every transaction of the build, in execution order.
+    pub fn message_hashes(&self) -> &[Hash] {
+        &self.message_hashes
+    }
+
+    /// State of the writable accounts after every instruction of the transaction other
+    /// than its compute budget and nonce instructions, see
+    /// `SimpleBuilder::record_intermediate_state`. Recording stops at the first failing
+    /// instruction, so a failed transaction has fewer states than instructions.
+    pub fn intermediate_states(&self) -> &[HashMap<Pubkey, AccountSharedData>] {
+        &self.intermediate_states
+    }
+
+    /// Compute units consumed by all transactions of the build, including the compute
+    /// budget instructions.
+    pub fn consumed_units(&self) -> u64 {
+        self.results().map(|summary| summary.executed_units).sum()
+    }
+
+    /// Summaries of every transaction of the build, in execution order. The raw results
+    /// remain available through `output`.
+    pub fn results(&self) -> impl Iterator<Item = TransactionSummary<'_>> {
+        self.output
+            .execution_results
+            .iter()
+            .enumerate()
+            .map(|(index, result)| match result {
+                TransactionExecutionResult::Executed { details, .. } => TransactionSummary {
+                    index,
+                    executed: true,
+                    status: details.status.clone(),
+                    executed_units: details.executed_units,
+                    logs: details.log_messages.as_deref().unwrap_or_default(),
+                    return_data: details.return_data.as_ref(),
+                },
+                TransactionExecutionResult::NotExecuted(e) => TransactionSummary {
+                    index,
+                    executed: false,
+                    status: Err(e.clone()),
+                    executed_units: 0,
+                    logs: &[],
+                    return_data: None,
+                },
+            })
+    }
+
+    /// Transactions executed successfully.
+    pub fn successful(&self) -> impl Iterator<Item = TransactionSummary<'_>> {
+        self.results().filter(TransactionSummary::is_ok)
+    }
+
+    /// Transactions that were not executed or failed during execution.
+    pub fn failed(&self) -> impl Iterator<Item = TransactionSummary<'_>> {
+        self.results().filter(|summary| !summary.is_ok())
+    }
+
+    pub fn fee_payer(&self) -> &FeePayerInfo {
+        &self.fee_payer
+    }
+
+    /// Returns a diagnostic if the transaction was not executed because its fee payer
+    /// could not pay the fee.
+    pub fn fee_payer_diagnostic(&self) -> Option<FeePayerDiagnostic> {
+        match self.output.execution_results.first()? {
+            TransactionExecutionResult::NotExecuted(
+                error @ (TransactionError::InsufficientFundsForFee
+                | TransactionError::InvalidAccountForFee
+                | TransactionError::AccountNotFound),
+            ) => Some(FeePayerDiagnostic {
+                fee_payer: self.fee_payer.fee_payer,
+                balance: self.fee_payer.balance,
+                required_fee: self.fee_payer.required_fee,
+                error: error.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Number of accounts referenced by the transaction, read-only ones included, that
+    /// existed before execution.
+    pub fn pre_account_count(&self) -> usize {
+        self.referenced_accounts
+            .iter()
+            .filter(|(_, existed)| *existed)
+            .count()
+    }
+
+    /// Number of accounts referenced by the transaction, read-only ones included, that
+    /// exist after execution. An account exists as long as it holds lamports, accounts of
+    /// a transaction that was not loaded keep their previous state.
+    pub fn post_account_count(&self) -> usize {
+        self.referenced_accounts
+            .iter()
+            .filter(|(key, existed)| {
+                self.loaded_account(key)
+                    .map_or(*existed, |account| exists(Some(account)))
+            })
+            .count()
+    }
+
+    /// Accounts created minus accounts closed by the transaction. A transaction can only
+    /// create or close accounts it references, so this is the change of the number of
+    /// accounts in the bank, including a read-only account closed by a program.
+    pub fn account_count_delta(&self) -> i64 {
+        self.post_account_count() as i64 - self.pre_account_count() as i64
+    }
+
+    /// Panics if the number of existing accounts did not change by `expected`, which
+    /// catches accidental account creation or closure.
+    pub fn assert_account_count_delta(&self, expected: i64) {
+        let delta = self.account_count_delta();
+        assert_eq!(
+            delta,
+            expected,
+            "account count changed by {} (from {} to {}), expected {}",
+            delta,
+            self.pre_account_count(),
+            self.post_account_count(),
+            expected
+        );
+    }
+
+    /// Lamport change of every writable account of the transaction, in account order.
+    /// Accounts of a transaction that was not loaded are unchanged.
+    pub fn lamport_deltas(&self) -> Vec<(Pubkey, i64)> {
+        self.pre_accounts
+            .iter()
+            .map(|(key, pre)| {
+                let pre = pre.as_ref().map_or(0, |account| account.lamports());
+                let post = self
+                    .loaded_account(key)
+                    .map_or(pre, |account| account.lamports());
+                (*key, post as i64 - pre as i64)
+            })
+            .collect()
+    }
+
+    /// Panics unless the only lamport change is the fee deducted from `fee_payer`, which
+    /// catches unintended transfers of programs that should be lamport-neutral.
+    pub fn assert_lamport_neutral(&self, fee_payer: &Pubkey) {
+        let fee = if fee_payer == &self.fee_payer.fee_payer {
+            self.fee_payer.required_fee as i64
+        } else {
+            0
+        };
+        for (key, delta) in self.lamport_deltas() {
+            if &key == fee_payer {
+                assert_eq!(
+                    delta, -fee,
+                    "fee payer {key} lamports changed by {delta}, expected the fee of {fee}"
+                );
+            } else {
+                assert_eq!(delta, 0, "account {key} lamports changed by {delta}");
+            }
+        }
+    }
+
+    /// Compute units consumed by every top-level instruction of the first transaction,
+    /// parsed from the `consumed` lines the runtime logs for programs. Builtins do not
+    /// report their consumption, so unless every top-level instruction did, only the
+    /// total is returned. With `SimpleBuilder::record_instruction_units` the units are
+    /// aligned to the instructions of the transaction instead, builtins included.
+    /// Empty if the transaction was not executed.
+    pub fn per_instruction_units(&self) -> Vec<u64> {
+        if !self.instruction_units.is_empty() {
+            return self.instruction_units.clone();
+        }
+        let Some(details) = self
+            .output
+            .execution_results
+            .first()
+            .and_then(|result| result.details())
+        else {
+            return vec![];
+        };
+
+        let logged = top_level_units(details.log_messages.as_deref().unwrap_or_default());
+        match logged.into_iter().collect::<Option<Vec<_>>>() {
+            Some(units) if !units.is_empty() => units,
+            _ => vec![details.executed_units],
+        }
+    }
+
+    /// Writable accounts whose post-execution state differs from their state before
+    /// execution.
+    pub fn modified_accounts(&self) -> Vec<(Pubkey, &AccountSharedData)> {
+        self.pre_accounts
+            .iter()
+            .filter_map(|(key, pre)| {
+                let post = self.loaded_account(key)?;
+                let modified = pre.as_ref().map_or(true, |pre| !accounts_equal(pre, post));
+                modified.then_some((*key, post))
+            })
+            .collect()
+    }
+
+    /// Formats status, logs, return data, consumed compute units and modified accounts
+    /// of the build into a multi-line report. Data longer than `MAX_REPORT_DATA_LEN`
+    /// bytes is truncated.
+    pub fn debug_report(&self) -> String {
+        let mut report = String::new();
+        // writing into a `String` never fails
+        self.write_report(&mut report).unwrap();
+        report
+    }
+
+    fn write_report(&self, report: &mut String) -> fmt::Result {
+        for (index, result) in self.output.execution_results.iter().enumerate() {
+            match result {
+                TransactionExecutionResult::Executed { details, .. } => {
+                    writeln!(report, "Transaction {index}: executed")?;
+                    writeln!(report, "  Status: {:?}", details.status)?;
+                    writeln!(report, "  Compute units: {}", details.executed_units)?;
+                    match details.return_data.as_ref() {
+                        Some(return_data) => {
+                            writeln!(
+                                report,
+                                "  Return data: {} {}{}",
+                                return_data.program_id,
+                                format_data(&return_data.data),
+                                if self.return_data_truncated {
+                                    TRUNCATED_MARKER
+                                } else {
+                                    ""
+                                }
+                            )?;
+                        }
+                        None => {
+                            writeln!(report, "  Return data: none")?;
+                        }
+                    }
+                    let logs = details.log_messages.as_deref().unwrap_or_default();
+                    writeln!(report, "  Logs ({}):", logs.len())?;
+                    for log in logs {
+                        writeln!(report, "    {log}")?;
+                    }
+                }
+                TransactionExecutionResult::NotExecuted(e) => {
+                    writeln!(report, "Transaction {index}: not executed")?;
+                    writeln!(report, "  Status: Err({e:?})")?;
+                    if let Some(diagnostic) = self.fee_payer_diagnostic() {
+                        writeln!(report, "  {diagnostic}")?;
+                    }
+                }
+            }
+        }
+
+        let modified = self.modified_accounts();
+        writeln!(report, "Modified accounts ({}):", modified.len())?;
+        for (key, account) in modified {
+            writeln!(
+                report,
+                "  {}: lamports {}, owner {}, executable {}, data {}",
+                key,
+                account.lamports(),
+                account.owner(),
+                account.executable(),
+                format_data(account.data())
+            )?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn loaded_account(&self, key: &Pubkey) -> Option<&AccountSharedData> {
+        self.output
+            .loaded_transactions
+            .iter()
+            .flatten()
+            .flat_map(|tx| tx.accounts.iter())
+            .find(|(loaded, _)| loaded == key)
+            .map(|(_, account)| account)
+    }
+
+    /// State of `key` before execution if it is a writable account of the transaction.
+    pub(crate) fn pre_account(&self, key: &Pubkey) -> Option<&AccountSharedData> {
+        self.pre_accounts
+            .iter()
+            .find(|(pre, _)| pre == key)
+            .and_then(|(_, account)| account.as_ref())
+    }
+}
+
+fn exists(account: Option<&AccountSharedData>) -> bool {
+    account.is_some_and(|account| account.lamports() > 0)
+}
+
+/// Cuts `logs` to at most `max` bytes plus the marker, returns whether anything was cut.
+fn truncate_logs(logs: &mut Vec<String>, max: usize) -> bool {
+    let mut remaining = max;
+    let Some(index) = logs.iter().position(|log| {
+        let crossing = log.len() > remaining;
+        if !crossing {
+            remaining -= log.len();
+        }
+        crossing
+    }) else {
+        return false;
+    };
+    let log = &mut logs[index];
+    let mut end = remaining;
+    while !log.is_char_boundary(end) {
+        end -= 1;
+    }
+    log.truncate(end);
+    log.push_str(TRUNCATED_MARKER);
+    logs.truncate(index + 1);
+    true
+}
+
+/// Parses `<program id> consumed <units> of <budget> compute units`.
+/// Compute units every invoked top-level instruction logged as consumed, `None` for the
+/// ones that did not log it, like builtins.
+fn top_level_units(logs: &[String]) -> Vec<Option<u64>> {
+    let mut depth = 0usize;
+    let mut units = vec![];
+    for log in logs {
+        let Some(rest) = log.strip_prefix("Program ") else {
+            continue;
+        };
+        if rest.contains(" invoke [") {
+            depth += 1;
+            if depth == 1 {
+                units.push(None);
+            }
+        } else if rest.ends_with(" success") || rest.contains(" failed: ") {
+            depth = depth.saturating_sub(1);
+        } else if depth == 1 {
+            if let (Some(consumed), Some(last)) = (parse_consumed_units(rest), units.last_mut()) {
+                *last = Some(consumed);
+            }
+        }
+    }
+    units
+}
+
+fn parse_consumed_units(log: &str) -> Option<u64> {
+    let (_, rest) = log.split_once(" consumed ")?;
+    let (units, rest) = rest.split_once(" of ")?;
+    rest.ends_with(" compute units")
+        .then(|| units.parse().ok())
+        .flatten()
+}
+
+fn format_data(data: &[u8]) -> String {
+    let hex = data
+        .iter()
+        .take(MAX_REPORT_DATA_LEN)
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    if data.len() > MAX_REPORT_DATA_LEN {
+        format!("({} bytes) 0x{}...", data.len(), hex)
+    } else {
+        format!("({} bytes) 0x{}", data.len(), hex)
+    }
+}
+
+impl Deref for BuildOutput {
+    type Target = LoadAndExecuteSanitizedTransactionsOutput;
+
+    fn deref(&self) -> &Self::Target {
+        &self.output
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use igloo_interface::l2::{
+    bank::{BankInfo, BankOperations},
+    executor::{Config, Init},
+};
+use solana_program_runtime::loaded_programs::ForkGraph;
+use solana_sdk::{
+    account::AccountSharedData, clock::Slot, hash::Hash, instruction::AccountMeta, pubkey::Pubkey,
+};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+use crate::{builder::output::BuildOutput, prelude::*};
+
+/// Number of threads `SimpleBuilder::build_parallel` builds on: the available cores, as
+/// every build only executes on its own in-memory bank.
+pub fn default_build_parallelism() -> usize {
+    thread::available_parallelism().map_or(1, |cores| cores.get())
+}
+
+/// Everything a single independent build of `SimpleBuilder::build_parallel` needs.
+#[derive(Clone, Default)]
+pub struct TxSpec {
+    pub program_path: Option<String>,
+    pub program_buffer: Option<Vec<u8>>,
+    pub accounts: Vec<(AccountMeta, Option<AccountSharedData>)>,
+    pub calldata: Vec<u8>,
+    pub v0_message: bool,
+}
+
+impl<B, C, FG> SimpleBuilder<B, FG>
+where
+    B: TransactionProcessingCallback
+        + BankOperations<Pubkey = Pubkey, AccountSharedData = AccountSharedData>
+        + BankInfo<Hash = Hash, Pubkey = Pubkey, Slot = Slot>
+        + Init<Config = C>,
+    C: Config + Sync,
+    FG: ForkGraph + Default,
+{
+    /// Executes every spec as an independent build on up to `default_build_parallelism`
+    /// threads and returns the results in input order. Each build gets its own bank created
+    /// from `cfg`, so builds cannot observe each other: shared mutable bank state is not
+    /// supported in this mode.
+    pub fn build_parallel(cfg: &C, specs: Vec<TxSpec>) -> Vec<Result<BuildOutput>> {
+        Self::build_parallel_ex(cfg, specs, default_build_parallelism())
+    }
+
+    /// Same as `build_parallel`, building on up to `threads` threads.
+    pub fn build_parallel_ex(
+        cfg: &C,
+        specs: Vec<TxSpec>,
+        threads: usize,
+    ) -> Vec<Result<BuildOutput>> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new((0..specs.len()).map(|_| None).collect::<Vec<_>>());
+
+        thread::scope(|scope| {
+            for _ in 0..threads.clamp(1, specs.len().max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(spec) = specs.get(index) else {
+                        break;
+                    };
+                    let result = Self::build_spec(cfg, spec);
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.expect("every spec is built"))
+            .collect()
+    }
+
+    fn build_spec(cfg: &C, spec: &TxSpec) -> Result<BuildOutput> {
+        let mut builder = Self::init(cfg)?;
+        for (meta, account) in spec.accounts.iter() {
+            builder.account(meta.clone(), account.clone());
+        }
+        builder
+            .program_path(spec.program_path.clone())
+            .program_buffer(spec.program_buffer.clone())
+            .calldata(spec.calldata.clone())
+            .v0_message(spec.v0_message)
+            .build()
+    }
+}
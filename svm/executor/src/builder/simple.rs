@@ -2,34 +2,72 @@ use std::{
     collections::{HashMap, HashSet},
     fs::{self, File},
     io::Read,
-    sync::{Arc, RwLock},
+    sync::{atomic::Ordering, Arc, OnceLock, RwLock},
 };
 
 use igloo_interface::l2::{
     bank::{BankInfo, BankOperations},
     executor::{Config, Init},
 };
+use solana_compute_budget::compute_budget_processor::process_compute_budget_instructions;
+use solana_ledger::blockstore::Blockstore;
+use solana_program_runtime::{
+    invoke_context::InvokeContext,
+    loaded_programs::ForkGraph,
+    solana_rbpf::{elf::Executable, verifier::RequisiteVerifier},
+};
 use solana_sdk::{
-    account::{AccountSharedData, WritableAccount},
-    clock::Slot,
-    hash::Hash,
-    instruction::AccountMeta,
+    account::{accounts_equal, AccountSharedData, ReadableAccount, WritableAccount},
+    address_lookup_table::{
+        self,
+        state::{AddressLookupTable, LookupTableMeta, LOOKUP_TABLE_META_SIZE},
+        AddressLookupTableAccount,
+    },
+    bpf_loader, bpf_loader_deprecated,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::{Clock, Epoch, Slot, UnixTimestamp},
+    compute_budget::{self, ComputeBudgetInstruction},
+    feature_set::FeatureSet,
+    fee::{FeeBudgetLimits, FeeStructure},
+    hash::{hash, Hash},
+    instruction::{AccountMeta, Instruction},
+    message::VersionedMessage,
+    native_loader,
+    nonce::{self, state::DurableNonce},
+    program::MAX_RETURN_DATA,
     pubkey::Pubkey,
+    rent::Rent,
+    rent_collector::RentCollector,
     signature::Signature,
-    transaction::VersionedTransaction,
+    system_instruction, system_program,
+    sysvar::SysvarId,
+    transaction::{SanitizedTransaction, TransactionError, VersionedTransaction},
+    vote,
 };
 use solana_svm::{
     account_loader::{CheckedTransactionDetails, TransactionCheckResult},
+    nonce_info::NoncePartial,
     transaction_processing_callback::TransactionProcessingCallback,
     transaction_processor::{
         ExecutionRecordingConfig, LoadAndExecuteSanitizedTransactionsOutput,
-        TransactionBatchProcessor, TransactionProcessingConfig,
+        TransactionBatchProcessor, TransactionProcessingConfig, TransactionProcessingEnvironment,
     },
+    transaction_results::TransactionExecutionResult,
 };
 
 use crate::{
-    builtin::register_builtins, env::create_executable_environment,
-    mock::fork_graph::MockForkGraph, prelude::*, transaction::builder::SanitizedTransactionBuilder,
+    builder::{
+        dsl::parse_account_metas,
+        output::{BuildOutput, CacheStats, ExecutionSummary, FeePayerInfo, ProgramDataInfo},
+    },
+    builtin::{register_builtin_accounts, register_builtins_ex, BuiltinSet},
+    env::{
+        create_custom_environment_ex, create_executable_environment, reset_syscall_counts,
+        set_return_data_cap, syscall_counts, DEPLOYMENT_SLOT,
+    },
+    mock::fork_graph::MockForkGraph,
+    prelude::*,
+    transaction::builder::{sanitize_transaction, SanitizedTransactionBuilder},
 };
 
 pub struct Settings {
@@ -42,18 +80,89 @@ pub struct ExecutionAccounts {
     pub signatures: HashMap<Pubkey, Signature>,
 }
 
-pub struct SimpleBuilder<B: TransactionProcessingCallback + BankOperations + BankInfo> {
+pub type TransactionCallback = Box<dyn FnMut(usize, &TransactionExecutionResult)>;
+
+/// Calldata and accounts of one transaction of a multi-transaction build, see
+/// `SimpleBuilder::transactions`.
+pub type TransactionGroup = (Vec<u8>, Vec<(AccountMeta, Option<AccountSharedData>)>);
+
+/// Processor along with its program cache, shared between builders, see
+/// `SimpleBuilder::program_cache`.
+pub struct SharedProgramCache<FG: ForkGraph = MockForkGraph> {
+    tx_processor: Arc<TransactionBatchProcessor<FG>>,
+    fork_graph: Arc<RwLock<FG>>,
+}
+
+// derived `Clone` would require `FG: Clone`
+impl<FG: ForkGraph> Clone for SharedProgramCache<FG> {
+    fn clone(&self) -> Self {
+        Self {
+            tx_processor: self.tx_processor.clone(),
+            fork_graph: self.fork_graph.clone(),
+        }
+    }
+}
+
+/// Builds and executes transactions against the bank `B`. The program cache of the
+/// processor resolves the relationship of slots with the fork graph `FG`, see
+/// `fork_graph`.
+pub struct SimpleBuilder<
+    B: TransactionProcessingCallback + BankOperations + BankInfo,
+    FG: ForkGraph = MockForkGraph,
+> {
     bank: B,
     settings: Settings,
     tx_builder: SanitizedTransactionBuilder,
-    tx_processor: Option<Arc<TransactionBatchProcessor<MockForkGraph>>>,
-    fork_graph: Arc<RwLock<MockForkGraph>>,
+    tx_processor: Option<Arc<TransactionBatchProcessor<FG>>>,
+    fork_graph: Arc<RwLock<FG>>,
 
     program_path: Option<String>,
     program_buffer: Option<Vec<u8>>,
     calldata: Vec<u8>,
     accounts: Vec<(AccountMeta, Option<AccountSharedData>)>,
     v0_message: bool,
+    strict_readonly: bool,
+    upgrade_authority: Option<Pubkey>,
+    upgradeable: bool,
+    program_id: Option<Pubkey>,
+    deployed_program_id: Option<Pubkey>,
+    skipped_sysvars: Vec<Pubkey>,
+    fee_payer: Option<Pubkey>,
+    fee_payer_seed: Option<Pubkey>,
+    fee_payer_count: u64,
+    funding_source: Option<Pubkey>,
+    on_transaction: Option<TransactionCallback>,
+    raw_instructions: Option<Vec<Instruction>>,
+    transactions: Option<Vec<TransactionGroup>>,
+    feature_set: Arc<FeatureSet>,
+    max_loaded_accounts_data_size: Option<u32>,
+    initial_compute_units: Option<u64>,
+    compute_unit_price: Option<u64>,
+    verify_program: bool,
+    address_lookup_tables: Vec<(Pubkey, Vec<Pubkey>)>,
+    record_cpi: bool,
+    max_batch_passes: usize,
+    max_log_bytes: Option<usize>,
+    max_return_data_bytes: Option<usize>,
+    return_data_cap: Option<usize>,
+    record_intermediate_state: bool,
+    record_instruction_units: bool,
+    last_cache_stats: CacheStats,
+    last_execution: Option<ExecutionSummary>,
+    last_accounts: HashMap<Pubkey, AccountSharedData>,
+    /// Accounts prepared by a running `simulate`, they are never written to the bank.
+    staged_accounts: Option<HashMap<Pubkey, AccountSharedData>>,
+    required_signers: Vec<Pubkey>,
+    expected_owners: Vec<(Pubkey, Pubkey)>,
+    sysvars: SysvarOverrides,
+    reuse_programs: bool,
+    fee_structure: Option<FeeStructure>,
+    nonce_account: Option<(Pubkey, Pubkey)>,
+    builtins: BuiltinSet,
+    rent_collector: Option<RentCollector>,
+    expired_blockhash: bool,
+    count_syscalls: bool,
+    last_syscall_counts: HashMap<String, u64>,
 
     check_result: Option<TransactionCheckResult>,
 }
@@ -66,13 +175,14 @@ impl Default for Settings {
     }
 }
 
-impl<B, C> Init for SimpleBuilder<B>
+impl<B, C, FG> Init for SimpleBuilder<B, FG>
 where
     B: TransactionProcessingCallback
         + BankOperations<Pubkey = Pubkey, AccountSharedData = AccountSharedData>
         + BankInfo<Hash = Hash, Pubkey = Pubkey, Slot = Slot>
         + Init<Config = C>,
     C: Config,
+    FG: ForkGraph + Default,
 {
     type Error = Error;
     type Config = C;
@@ -93,39 +203,585 @@ where
             calldata: Default::default(),
             accounts: Default::default(),
             v0_message: Default::default(),
+            strict_readonly: Default::default(),
+            upgrade_authority: Default::default(),
+            upgradeable: Default::default(),
+            program_id: Default::default(),
+            deployed_program_id: Default::default(),
+            skipped_sysvars: Default::default(),
+            fee_payer: Default::default(),
+            fee_payer_seed: Default::default(),
+            fee_payer_count: Default::default(),
+            funding_source: Default::default(),
+            on_transaction: Default::default(),
+            raw_instructions: Default::default(),
+            transactions: Default::default(),
+            feature_set: Default::default(),
+            max_loaded_accounts_data_size: Default::default(),
+            initial_compute_units: Default::default(),
+            compute_unit_price: Default::default(),
+            verify_program: true,
+            address_lookup_tables: Default::default(),
+            record_cpi: Default::default(),
+            max_batch_passes: Default::default(),
+            max_log_bytes: Default::default(),
+            max_return_data_bytes: Default::default(),
+            return_data_cap: Default::default(),
+            record_intermediate_state: Default::default(),
+            record_instruction_units: Default::default(),
+            last_cache_stats: Default::default(),
+            last_execution: Default::default(),
+            last_accounts: Default::default(),
+            staged_accounts: Default::default(),
+            required_signers: Default::default(),
+            expected_owners: Default::default(),
+            sysvars: Default::default(),
+            reuse_programs: Default::default(),
+            fee_structure: Default::default(),
+            nonce_account: Default::default(),
+            builtins: Default::default(),
+            rent_collector: Default::default(),
+            expired_blockhash: Default::default(),
+            count_syscalls: Default::default(),
+            last_syscall_counts: Default::default(),
             check_result: Default::default(),
         })
     }
 }
 
-impl<B> SimpleBuilder<B>
+impl<B, C, FG> SimpleBuilder<B, FG>
+where
+    B: TransactionProcessingCallback
+        + BankOperations<Pubkey = Pubkey, AccountSharedData = AccountSharedData>
+        + BankInfo<Hash = Hash, Pubkey = Pubkey, Slot = Slot>
+        + Init<Config = C>,
+    C: Config,
+    FG: ForkGraph + Default,
+{
+    /// Runs the build once with `features` deactivated and once with them activated,
+    /// returning whether they were active along with each output. Every run starts from
+    /// a fresh bank created with `cfg`, usually the config the builder was initialized
+    /// with, so runs cannot affect each other. The builder keeps the bank of the last run,
+    /// its feature set is restored.
+    pub fn run_feature_matrix(
+        &mut self,
+        cfg: &C,
+        features: Vec<Pubkey>,
+    ) -> Result<Vec<(bool, BuildOutput)>> {
+        let original = self.feature_set.clone();
+        let outputs = [false, true]
+            .into_iter()
+            .map(|activated| {
+                self.reset_bank(cfg)?;
+                for feature in features.iter() {
+                    if activated {
+                        self.activate_feature(*feature);
+                    } else {
+                        self.deactivate_feature(*feature);
+                    }
+                }
+                Ok((activated, self.build()?))
+            })
+            .collect::<Result<Vec<_>>>();
+        self.feature_set = original;
+        outputs
+    }
+
+    fn reset_bank(&mut self, cfg: &C) -> Result<()> {
+        self.bank = B::init(cfg).map_err(|e| Error::BuilderError(e.to_string()))?;
+        // the processor and the deployed program belong to the previous bank
+        self.tx_processor = None;
+        self.fork_graph = Default::default();
+        self.deployed_program_id = None;
+        self.fee_payer_count = 0;
+        Ok(())
+    }
+}
+
+impl<B, FG> SimpleBuilder<B, FG>
 where
     B: TransactionProcessingCallback
         + BankOperations<Pubkey = Pubkey, AccountSharedData = AccountSharedData>
         + BankInfo<Hash = Hash, Pubkey = Pubkey, Slot = Slot>,
+    FG: ForkGraph,
 {
-    pub fn build(&mut self) -> Result<LoadAndExecuteSanitizedTransactionsOutput> {
+    /// Builds and executes like `build` without changing the bank. The accounts prepared
+    /// for the build and deployments are staged in an overlay that is discarded
+    /// afterwards and the slot is not bumped, so repeated simulations start from the same
+    /// state. Results of the last build, e.g. `last_execution` and `account_after`, are
+    /// left untouched. Builtin and sysvar accounts registered when the processor is
+    /// created are written to the bank like for any build.
+    pub fn simulate(&mut self) -> Result<LoadAndExecuteSanitizedTransactionsOutput> {
+        let deployed_program_id = self.deployed_program_id;
+        let fee_payer_count = self.fee_payer_count;
+        self.staged_accounts = Some(HashMap::new());
+        let result = self.build();
+        self.staged_accounts = None;
+        self.deployed_program_id = deployed_program_id;
+        self.fee_payer_count = fee_payer_count;
+        Ok(result?.output)
+    }
+
+    pub fn build(&mut self) -> Result<BuildOutput> {
+        if let Some(groups) = self.transactions.clone() {
+            return self.build_transactions(groups);
+        }
         let (result, _) = self.build_ex()?;
         Ok(result)
     }
 
-    pub fn build_ex(
+    pub fn build_ex(&mut self) -> Result<(BuildOutput, VersionedTransaction)> {
+        let (sanitized_transaction, versioned_transaction, readonly_keys) =
+            self.prepare_transaction()?;
+        if self.staged_accounts.is_none() {
+            let message = &versioned_transaction.message;
+            self.required_signers = message.static_account_keys()
+                [..message.header().num_required_signatures as usize]
+                .to_vec();
+        }
+        let output = self.execute(sanitized_transaction, &readonly_keys)?;
+        Ok((output, versioned_transaction))
+    }
+
+    /// Builds the transaction the way `build_ex` does without executing it, e.g. to have
+    /// it signed externally, and returns it along with the hash of its message. The
+    /// transaction can be executed with `build_batch` afterwards.
+    pub fn build_unexecuted(&mut self) -> Result<(VersionedTransaction, Hash)> {
+        let (sanitized_transaction, versioned_transaction, _) = self.prepare_transaction()?;
+        Ok((versioned_transaction, *sanitized_transaction.message_hash()))
+    }
+
+    fn prepare_transaction(
         &mut self,
-    ) -> Result<(
-        LoadAndExecuteSanitizedTransactionsOutput,
-        VersionedTransaction,
-    )> {
+    ) -> Result<(SanitizedTransaction, VersionedTransaction, Vec<Pubkey>)> {
+        if self.transactions.is_some() {
+            return Err(Error::BuilderError(
+                "build_ex builds a single transaction, use build with multiple transactions"
+                    .to_string(),
+            ));
+        }
+        self.bump_bank()?;
+        self.register_lookup_tables()?;
+        let advance_nonce = self.prepare_nonce_account()?;
+
+        match self.raw_instructions.clone() {
+            Some(instructions) => self.build_raw_transaction(instructions, advance_nonce),
+            None => self.build_transaction(advance_nonce),
+        }
+    }
+
+    fn build_transactions(&mut self, groups: Vec<TransactionGroup>) -> Result<BuildOutput> {
+        self.bump_bank()?;
+        self.register_lookup_tables()?;
+
+        let program_id = self.deploy()?;
+        let mut transactions = vec![];
+        let mut readonly = vec![];
+        for (calldata, metas) in groups {
+            let accounts = self.prepare_accounts_from(metas)?;
+            readonly.extend(readonly_keys(&accounts.accounts));
+            for instruction in self.compute_budget_instructions() {
+                self.tx_builder.create_instruction(
+                    instruction.program_id,
+                    instruction.accounts,
+                    HashMap::new(),
+                    instruction.data,
+                );
+            }
+            self.tx_builder.create_instruction(
+                program_id,
+                accounts.accounts,
+                accounts.signatures,
+                calldata,
+            );
+            let (sanitized_transaction, _) = self.tx_builder.build(
+                self.recent_blockhash(),
+                (accounts.fee_payer, Signature::new_unique()),
+                self.v0_message,
+            )?;
+            transactions.push(sanitized_transaction);
+        }
+        // accounts written by one transaction may be read by a later one
+        readonly.retain(|key| {
+            !transactions
+                .iter()
+                .any(|transaction| writable_keys(transaction).contains(key))
+        });
+        self.execute_all(transactions, &readonly)
+    }
+
+    /// Executes the transactions recorded in `slot` of the blockstore one by one in the
+    /// builder's environment, in ledger order. Vote transactions are skipped. Results are
+    /// not committed to the bank, so every transaction executes against the bank state
+    /// before the replay, and their fee payers have to exist in the bank.
+    pub fn replay_slot(&mut self, blockstore: &Blockstore, slot: Slot) -> Result<Vec<BuildOutput>> {
+        let transactions = blockstore
+            .get_slot_entries(slot, 0)
+            .map_err(|e| Error::BuilderError(format!("Failed to read slot {slot}: {e}")))?
+            .into_iter()
+            .flat_map(|entry| entry.transactions)
+            .filter(|tx| !is_vote_transaction(tx))
+            .collect::<Vec<_>>();
+
         self.bank
             .bump()
             .map_err(|e| Error::BuilderError(e.to_string()))?;
+        transactions
+            .into_iter()
+            .map(|tx| self.execute(sanitize_transaction(tx)?, &[]))
+            .collect()
+    }
 
-        let buffer = self.read_program()?;
-        let program_id = self
-            .bank
-            .deploy_program(buffer)
+    /// Executes `transactions` one by one against the builder's bank, in order. Unlike
+    /// other builds, the writable accounts of every successfully executed transaction are
+    /// written back to the bank, so later transactions observe their effects. Accounts
+    /// added to the builder are inserted before the first transaction.
+    ///
+    /// With [`Self::retry_on_missing_account`] transactions that could not be loaded
+    /// because an account was missing are executed again in subsequent passes. Returns
+    /// the output of the last attempt of every transaction, in input order. The
+    /// `on_transaction` callback is invoked once per transaction with its index in the
+    /// batch and the result of its last attempt, in input order, after the last pass.
+    pub fn build_batch(
+        &mut self,
+        transactions: Vec<VersionedTransaction>,
+    ) -> Result<Vec<BuildOutput>> {
+        self.bank
+            .bump()
             .map_err(|e| Error::BuilderError(e.to_string()))?;
+        for (meta, account) in self.accounts.iter() {
+            if let Some(account) = account {
+                self.bank
+                    .insert_account(meta.pubkey, account.clone())
+                    .map_err(|e| Error::BuilderError(e.to_string()))?;
+            }
+        }
+
+        let transactions = transactions
+            .into_iter()
+            .map(sanitize_transaction)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        // single executions would notify every attempt as the first transaction
+        let on_transaction = self.on_transaction.take();
+        let outputs = self.execute_batch(transactions);
+        self.on_transaction = on_transaction;
+        let outputs = outputs?;
+        if let Some(callback) = self.on_transaction.as_mut() {
+            for (index, output) in outputs.iter().enumerate() {
+                if let Some(result) = output.execution_results.first() {
+                    callback(index, result);
+                }
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Executes `transactions` in passes for `build_batch`, committing the accounts of
+    /// every successful one.
+    fn execute_batch(
+        &mut self,
+        transactions: Vec<SanitizedTransaction>,
+    ) -> Result<Vec<BuildOutput>> {
+        let mut outputs = transactions.iter().map(|_| None).collect::<Vec<_>>();
+        let mut pending = (0..transactions.len()).collect::<Vec<_>>();
+        // the batch is stable once a pass commits no transaction, as the bank is unchanged
+        // and a further pass would fail the same way
+        for _ in 0..self.max_batch_passes.max(1) {
+            let mut committed = false;
+            let mut retry = vec![];
+            for index in pending {
+                let output = self.execute(transactions[index].clone(), &[])?;
+                let executed = output
+                    .execution_results
+                    .first()
+                    .and_then(|result| result.details())
+                    .is_some_and(|details| details.status.is_ok());
+                if executed {
+                    self.commit_accounts(&output)?;
+                    committed = true;
+                } else if is_missing_account(&output) {
+                    retry.push(index);
+                }
+                outputs[index] = Some(output);
+            }
+
+            pending = retry;
+            if pending.is_empty() || !committed {
+                break;
+            }
+        }
+
+        Ok(outputs
+            .into_iter()
+            .map(|output| output.expect("every transaction is executed"))
+            .collect())
+    }
+
+    fn commit_accounts(&mut self, output: &BuildOutput) -> Result<()> {
+        for (key, account) in output.modified_accounts() {
+            self.bank
+                .insert_account(key, account.clone())
+                .map_err(|e| Error::BuilderError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn execute(
+        &mut self,
+        sanitized_transaction: SanitizedTransaction,
+        readonly_keys: &[Pubkey],
+    ) -> Result<BuildOutput> {
+        self.execute_all(vec![sanitized_transaction], readonly_keys)
+    }
+
+    /// Executes `transactions` in order as one batch. Later transactions observe the
+    /// writable accounts of earlier successful ones, nothing is committed to the bank.
+    fn execute_all(
+        &mut self,
+        transactions: Vec<SanitizedTransaction>,
+        readonly_keys: &[Pubkey],
+    ) -> Result<BuildOutput> {
+        self.verify_owners()?;
+        let check_results = transactions
+            .iter()
+            .map(|_| self.get_checked_tx_details())
+            .collect::<Vec<_>>();
+        let first = transactions
+            .first()
+            .ok_or(Error::BuilderError("No transaction to execute".to_string()))?;
+
+        self.ensure_tx_processor()?;
+
+        // builtin accounts are registered while creating the processor, so the snapshot
+        // must be taken afterwards
+        let readonly_snapshot = self.snapshot_readonly_accounts(readonly_keys);
+        let mut pre_accounts: Vec<(Pubkey, Option<AccountSharedData>)> = vec![];
+        for key in transactions.iter().flat_map(writable_keys) {
+            if !pre_accounts.iter().any(|(pre, _)| *pre == key) {
+                pre_accounts.push((key, self.load_account(&key)));
+            }
+        }
+        let mut referenced_accounts: Vec<(Pubkey, bool)> = vec![];
+        for transaction in transactions.iter() {
+            for key in transaction.message().account_keys().iter() {
+                if !referenced_accounts
+                    .iter()
+                    .any(|(referenced, _)| referenced == key)
+                {
+                    let exists = self
+                        .load_account(key)
+                        .is_some_and(|account| account.lamports() > 0);
+                    referenced_accounts.push((*key, exists));
+                }
+            }
+        }
+        let fee_payer = self.fee_payer_info(first, &check_results[0]);
+        let processing_config = self.get_processing_config();
+        let processing_environment = self.get_processing_environment();
+        let cache_stats = self.cache_stats()?;
+        if self.count_syscalls {
+            reset_syscall_counts();
+        }
+        set_return_data_cap(self.return_data_cap.unwrap_or(MAX_RETURN_DATA));
+        let message_hashes = transactions
+            .iter()
+            .map(|transaction| *transaction.message_hash())
+            .collect();
+        let recorded_transaction =
+            (self.record_intermediate_state && transactions.len() == 1).then(|| first.clone());
+        let recorded_instructions = first.message().instructions().len();
+        let processor = self
+            .tx_processor
+            .as_ref()
+            .ok_or(Error::TransactionProcessorIsNone)?;
+        let mut committed = self.staged_accounts.clone().unwrap_or_default();
+        let mut result: Option<LoadAndExecuteSanitizedTransactionsOutput> = None;
+        for (transaction, check_result) in transactions.iter().zip(check_results) {
+            let keys = writable_keys(transaction);
+            let output = processor.load_and_execute_sanitized_transactions(
+                &BatchOverlay {
+                    inner: &self.bank,
+                    accounts: &committed,
+                },
+                std::slice::from_ref(transaction),
+                vec![check_result],
+                &processing_environment,
+                &processing_config,
+            );
+            let executed = output
+                .execution_results
+                .first()
+                .and_then(|result| result.details())
+                .is_some_and(|details| details.status.is_ok());
+            if let Some(Ok(loaded)) = output.loaded_transactions.first().filter(|_| executed) {
+                committed.extend(
+                    loaded
+                        .accounts
+                        .iter()
+                        .filter(|(key, _)| keys.contains(key))
+                        .cloned(),
+                );
+            }
+            match result.as_mut() {
+                Some(result) => {
+                    result.error_metrics.accumulate(&output.error_metrics);
+                    result.execute_timings.accumulate(&output.execute_timings);
+                    result.execution_results.extend(output.execution_results);
+                    result
+                        .loaded_transactions
+                        .extend(output.loaded_transactions);
+                }
+                None => result = Some(output),
+            }
+        }
+        let result = result.expect("at least one transaction is executed");
+        let simulated = self.staged_accounts.is_some();
+        if !simulated {
+            self.last_cache_stats = self.cache_stats()?.since(&cache_stats);
+            if self.count_syscalls {
+                self.last_syscall_counts = syscall_counts();
+            }
+        }
+        if let Some(snapshot) = readonly_snapshot {
+            verify_readonly_accounts(&snapshot, &result)?;
+        }
+        self.notify_transactions(&result);
+
+        let mut output = BuildOutput::new(result, pre_accounts, fee_payer);
+        output.set_referenced_accounts(referenced_accounts);
+        output.set_message_hashes(message_hashes);
+        if self.record_instruction_units {
+            output.record_instruction_units(recorded_instructions);
+        }
+        output.truncate(self.max_log_bytes, self.max_return_data_bytes);
+        if !simulated {
+            self.last_execution = output.results().last().map(ExecutionSummary::from);
+            self.last_accounts = output
+                .modified_accounts()
+                .into_iter()
+                .map(|(key, account)| (key, account.clone()))
+                .collect();
+        }
+        if let Some(transaction) = recorded_transaction {
+            output.set_intermediate_states(self.intermediate_states(&transaction)?);
+        }
+        Ok(output)
+    }
+
+    /// The processor has no hook between instructions, so the state after instruction
+    /// `i` is captured by executing the transaction truncated to its first `i + 1`
+    /// instructions. The compute budget and nonce instructions are kept in every prefix
+    /// and get no state of their own. Nothing is committed, every prefix runs against the
+    /// same bank state.
+    fn intermediate_states(
+        &self,
+        transaction: &SanitizedTransaction,
+    ) -> Result<Vec<HashMap<Pubkey, AccountSharedData>>> {
+        let message = transaction.message();
+        let writable_keys = writable_keys(transaction);
+        let versioned_transaction = transaction.to_versioned_transaction();
+        let setup = message
+            .program_instructions_iter()
+            .enumerate()
+            .map(|(index, (program_id, _))| {
+                (index == 0 && transaction.get_durable_nonce().is_some())
+                    || program_id == &compute_budget::id()
+            })
+            .collect::<Vec<_>>();
+
+        let mut states = vec![];
+        for last in (0..setup.len()).filter(|index| !setup[*index]) {
+            let result = self.execute_prefix(&versioned_transaction, |index| {
+                setup[index] || index <= last
+            })?;
+            let executed = result
+                .execution_results
+                .first()
+                .and_then(|result| result.details())
+                .is_some_and(|details| details.status.is_ok());
+            let Some(Ok(loaded)) = result.loaded_transactions.first().filter(|_| executed) else {
+                break;
+            };
+            states.push(
+                loaded
+                    .accounts
+                    .iter()
+                    .filter(|(key, _)| writable_keys.contains(key))
+                    .cloned()
+                    .collect(),
+            );
+        }
+        Ok(states)
+    }
+
+    /// Executes `transaction` with only the instructions at the indices `keep` accepts,
+    /// nothing is committed.
+    fn execute_prefix(
+        &self,
+        transaction: &VersionedTransaction,
+        keep: impl Fn(usize) -> bool,
+    ) -> Result<LoadAndExecuteSanitizedTransactionsOutput> {
+        let mut prefix = transaction.clone();
+        let instructions = match &mut prefix.message {
+            VersionedMessage::Legacy(message) => &mut message.instructions,
+            VersionedMessage::V0(message) => &mut message.instructions,
+        };
+        let mut index = 0;
+        instructions.retain(|_| {
+            index += 1;
+            keep(index - 1)
+        });
+        let processor = self
+            .tx_processor
+            .as_ref()
+            .ok_or(Error::TransactionProcessorIsNone)?;
+        let no_accounts = HashMap::new();
+        Ok(processor.load_and_execute_sanitized_transactions(
+            &BatchOverlay {
+                inner: &self.bank,
+                accounts: self.staged_accounts.as_ref().unwrap_or(&no_accounts),
+            },
+            &[sanitize_transaction(prefix)?],
+            vec![self.get_checked_tx_details()],
+            &self.get_processing_environment(),
+            &self.get_processing_config(),
+        ))
+    }
+
+    fn build_transaction(
+        &mut self,
+        advance_nonce: Option<Instruction>,
+    ) -> Result<(SanitizedTransaction, VersionedTransaction, Vec<Pubkey>)> {
+        let program_id = self.deploy()?;
 
         let accounts = self.prepare_accounts()?;
+        let readonly_keys = readonly_keys(&accounts.accounts);
+        if let Some(advance_nonce) = advance_nonce {
+            // the nonce instruction invokes another program, which is compiled as a whole
+            let mut instructions = vec![advance_nonce];
+            instructions.extend(self.compute_budget_instructions());
+            instructions.push(Instruction::new_with_bytes(
+                program_id,
+                &self.calldata,
+                accounts.accounts,
+            ));
+            let (sanitized_transaction, versioned_transaction) =
+                self.tx_builder.build_from_instructions(
+                    &instructions,
+                    self.recent_blockhash(),
+                    (accounts.fee_payer, Signature::new_unique()),
+                    self.v0_message,
+                )?;
+            return Ok((sanitized_transaction, versioned_transaction, readonly_keys));
+        }
+        for instruction in self.compute_budget_instructions() {
+            self.tx_builder.create_instruction(
+                instruction.program_id,
+                instruction.accounts,
+                HashMap::new(),
+                instruction.data,
+            );
+        }
         self.tx_builder.create_instruction(
             program_id,
             accounts.accounts,
@@ -134,33 +790,180 @@ where
         );
 
         let (sanitized_transaction, versioned_transaction) = self.tx_builder.build(
-            self.bank.last_blockhash(),
+            self.recent_blockhash(),
             (accounts.fee_payer, Signature::new_unique()),
             self.v0_message,
         )?;
-        let check_result = self.get_checked_tx_details();
+        Ok((sanitized_transaction, versioned_transaction, readonly_keys))
+    }
 
-        if self.tx_processor.is_none() {
-            self.tx_processor = Some(Arc::new(create_transaction_processor(
-                &mut self.bank,
-                self.fork_graph.clone(),
-            )?));
+    fn build_raw_transaction(
+        &mut self,
+        mut instructions: Vec<Instruction>,
+        advance_nonce: Option<Instruction>,
+    ) -> Result<(SanitizedTransaction, VersionedTransaction, Vec<Pubkey>)> {
+        instructions.splice(
+            0..0,
+            advance_nonce
+                .into_iter()
+                .chain(self.compute_budget_instructions()),
+        );
+        // builtins are registered while creating the processor
+        self.ensure_tx_processor()?;
+        for instruction in instructions.iter() {
+            // deployed programs are owned by one of the bpf loaders, builtins by the
+            // native loader
+            let loadable = self
+                .load_account(&instruction.program_id)
+                .is_some_and(|account| {
+                    is_program(&account) || account.owner() == &native_loader::id()
+                });
+            if !loadable {
+                return Err(Error::BuilderError(format!(
+                    "Program {} is neither deployed nor a builtin",
+                    instruction.program_id
+                )));
+            }
         }
 
+        let accounts = self.prepare_accounts()?;
+        let metas = instructions
+            .iter()
+            .flat_map(|instruction| instruction.accounts.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        let readonly_keys = readonly_keys(&metas)
+            .into_iter()
+            .filter(|key| {
+                !metas
+                    .iter()
+                    .any(|meta| meta.is_writable && meta.pubkey == *key)
+            })
+            .collect();
+
+        let (sanitized_transaction, versioned_transaction) =
+            self.tx_builder.build_from_instructions(
+                &instructions,
+                self.recent_blockhash(),
+                (accounts.fee_payer, Signature::new_unique()),
+                self.v0_message,
+            )?;
+        Ok((sanitized_transaction, versioned_transaction, readonly_keys))
+    }
+
+    /// Upgrades the program deployed with an upgrade authority to `buffer` by executing
+    /// the upgradeable loader's upgrade instruction. On success the resulting accounts are
+    /// written back to the bank, so following builds execute the new program.
+    pub fn upgrade_program(
+        &mut self,
+        buffer: Vec<u8>,
+        authority: Pubkey,
+    ) -> Result<LoadAndExecuteSanitizedTransactionsOutput> {
+        let program_id = self.deployed_program_id.ok_or(Error::BuilderError(
+            "Program not deployed with an upgrade authority".into(),
+        ))?;
+        self.bank
+            .bump()
+            .map_err(|e| Error::BuilderError(e.to_string()))?;
+
+        let program = self.bank.get_account_shared_data(&program_id);
+        let programdata_address = match program.map(|account| bincode::deserialize(account.data()))
+        {
+            Some(Ok(UpgradeableLoaderState::Program {
+                programdata_address,
+            })) => programdata_address,
+            _ => {
+                return Err(Error::BuilderError(format!(
+                    "Invalid upgradeable program account: {program_id}"
+                )))
+            }
+        };
+        let programdata = self
+            .bank
+            .get_account_shared_data(&programdata_address)
+            .ok_or(Error::BuilderError("Program data account not found".into()))?;
+
+        // the buffer tops up the program data account to be rent exempt, what remains is
+        // moved to the spill account and keeps it rent exempt as well
+        let rent = self.rent_sysvar();
+        let buffer_len = UpgradeableLoaderState::size_of_buffer(buffer.len());
+        let required_lamports = rent.minimum_balance(programdata.data().len()).max(1);
+        let buffer_address = Pubkey::new_unique();
+        let mut buffer_account = AccountSharedData::new_data(
+            required_lamports
+                .saturating_sub(programdata.lamports())
+                .saturating_add(rent.minimum_balance(buffer_len)),
+            &UpgradeableLoaderState::Buffer {
+                authority_address: Some(authority),
+            },
+            &bpf_loader_upgradeable::id(),
+        )
+        .map_err(|e| Error::BuilderError(e.to_string()))?;
+        let mut data = buffer_account.data().to_vec();
+        data.resize(UpgradeableLoaderState::size_of_buffer_metadata(), 0);
+        data.extend_from_slice(&buffer);
+        buffer_account.set_data(data);
+        self.bank
+            .insert_account(buffer_address, buffer_account)
+            .map_err(|e| Error::BuilderError(e.to_string()))?;
+
+        let spill_address = Pubkey::new_unique();
+        let instruction = bpf_loader_upgradeable::upgrade(
+            &program_id,
+            &buffer_address,
+            &authority,
+            &spill_address,
+        );
+        let signatures = HashMap::from([(authority, Signature::new_unique())]);
+        self.tx_builder.create_instruction(
+            instruction.program_id,
+            instruction.accounts,
+            signatures,
+            instruction.data,
+        );
+        let fee_payer = self.create_fee_payer()?;
+        let (sanitized_transaction, _) = self.tx_builder.build(
+            self.bank.last_blockhash(),
+            (fee_payer, Signature::new_unique()),
+            self.v0_message,
+        )?;
+
+        self.ensure_tx_processor()?;
         let processing_config = self.get_processing_config();
-        Ok((
-            self.tx_processor
-                .as_ref()
-                .ok_or(Error::TransactionProcessorIsNone)?
-                .load_and_execute_sanitized_transactions(
-                    &self.bank,
-                    &[sanitized_transaction],
-                    vec![check_result],
-                    &Default::default(),
-                    &processing_config,
-                ),
-            versioned_transaction,
-        ))
+        let result = self
+            .tx_processor
+            .as_ref()
+            .ok_or(Error::TransactionProcessorIsNone)?
+            .load_and_execute_sanitized_transactions(
+                &self.bank,
+                &[sanitized_transaction],
+                vec![self.get_checked_tx_details()],
+                &self.get_processing_environment(),
+                &processing_config,
+            );
+
+        let upgraded = result
+            .execution_results
+            .first()
+            .and_then(|result| result.details())
+            .is_some_and(|details| details.status.is_ok());
+        if upgraded {
+            let updated = [programdata_address, program_id, buffer_address];
+            for (key, account) in result
+                .loaded_transactions
+                .iter()
+                .flatten()
+                .flat_map(|tx| tx.accounts.iter().filter(|(key, _)| updated.contains(key)))
+            {
+                self.bank
+                    .insert_account(*key, account.clone())
+                    .map_err(|e| Error::BuilderError(e.to_string()))?;
+            }
+            // the cached program is stale now
+            self.tx_processor = None;
+        }
+
+        Ok(result)
     }
 
     pub fn settings(&mut self, settings: Settings) -> &mut Self {
@@ -177,20 +980,49 @@ where
         &self.bank
     }
 
-    pub fn tx_processor(
-        &mut self,
-        tx_processor: Arc<TransactionBatchProcessor<MockForkGraph>>,
-    ) -> &mut Self {
+    pub(crate) fn bank_mut(&mut self) -> &mut B {
+        &mut self.bank
+    }
+
+    pub fn tx_processor(&mut self, tx_processor: Arc<TransactionBatchProcessor<FG>>) -> &mut Self {
         self.tx_processor = Some(tx_processor);
         self
     }
 
+    /// Processor of the builder along with its program cache, created if needed, to be
+    /// shared with other builders through `program_cache`.
+    pub fn shared_program_cache(&mut self) -> Result<SharedProgramCache<FG>> {
+        self.ensure_tx_processor()?;
+        Ok(SharedProgramCache {
+            tx_processor: self
+                .tx_processor
+                .clone()
+                .ok_or(Error::TransactionProcessorIsNone)?,
+            fork_graph: self.fork_graph.clone(),
+        })
+    }
+
+    /// Executes with the processor of `cache`, e.g. one warmed up by another builder. The
+    /// program is then deployed once per bank at an id derived from the hash of its ELF,
+    /// so repeated builds of the same ELF reuse the program compiled in the cache, while a
+    /// changed buffer is deployed at another id and compiled again. The processor keeps
+    /// the sysvars of the bank it was created with.
+    pub fn program_cache(&mut self, cache: SharedProgramCache<FG>) -> &mut Self {
+        self.tx_processor = Some(cache.tx_processor);
+        self.fork_graph = cache.fork_graph;
+        self.reuse_programs = true;
+        self
+    }
+
     pub fn tx_builder(&mut self, tx_builder: SanitizedTransactionBuilder) -> &mut Self {
         self.tx_builder = tx_builder;
         self
     }
 
-    pub fn fork_graph(&mut self, fork_graph: Arc<RwLock<MockForkGraph>>) -> &mut Self {
+    /// Fork graph of the processor created by the following build, e.g. to test how
+    /// cached programs are invalidated across forks. A processor set with `tx_processor`
+    /// or `program_cache` keeps its own fork graph.
+    pub fn fork_graph(&mut self, fork_graph: Arc<RwLock<FG>>) -> &mut Self {
         self.fork_graph = fork_graph;
         self
     }
@@ -215,11 +1047,343 @@ where
         self
     }
 
+    /// When enabled, the state of every read-only account is captured before execution
+    /// and the build fails if any of them was modified by the transaction.
+    pub fn strict_readonly(&mut self, value: bool) -> &mut Self {
+        self.strict_readonly = value;
+        self
+    }
+
+    /// Deploys the program once with the given upgrade authority and keeps using it for
+    /// following builds, so it can be upgraded with `upgrade_program`. Shorthand for
+    /// `upgradeable(authority)` with `Some` authority, a `None` authority turns deploying
+    /// once off again.
+    pub fn upgrade_authority(&mut self, authority: Option<Pubkey>) -> &mut Self {
+        self.upgradeable = authority.is_some();
+        self.upgrade_authority = authority;
+        self
+    }
+
+    /// Deploys the program once with the upgradeable loader, creating rent-exempt program
+    /// and ProgramData accounts, and keeps using it for following builds. Unlike
+    /// `upgrade_authority`, a `None` authority deploys an immutable program, e.g. to test
+    /// programs inspecting their own ProgramData account. Both set the same authority, so
+    /// the last call of the two wins.
+    pub fn upgradeable(&mut self, authority: Option<Pubkey>) -> &mut Self {
+        self.upgradeable = true;
+        self.upgrade_authority = authority;
+        self
+    }
+
+    /// Activates `feature` for the transactions executed by following builds. All
+    /// features are inactive by default. The feature set is passed to the processor with
+    /// every execution, so toggling takes effect even after the processor was created.
+    pub fn activate_feature(&mut self, feature: Pubkey) -> &mut Self {
+        Arc::make_mut(&mut self.feature_set).activate(&feature, 0);
+        self
+    }
+
+    /// Deactivates `feature` for the transactions executed by following builds, see
+    /// `activate_feature`.
+    pub fn deactivate_feature(&mut self, feature: Pubkey) -> &mut Self {
+        Arc::make_mut(&mut self.feature_set).deactivate(&feature);
+        self
+    }
+
+    /// Features active in the environment passed to the processor. There are no
+    /// cluster-implied features to add: the builder is not tied to a cluster type and the
+    /// processor runs with exactly this set, so features activated in the bank's genesis
+    /// do not apply to the built transactions. Only features toggled on the builder are
+    /// returned.
+    pub fn active_features(&self) -> HashSet<Pubkey> {
+        self.feature_set.active.keys().copied().collect()
+    }
+
+    /// Limits the total size of the account data loaded by the transaction, by adding a
+    /// compute budget instruction to it. Without it the runtime's default limit applies.
+    pub fn max_loaded_accounts_data_size(&mut self, bytes: u32) -> &mut Self {
+        self.max_loaded_accounts_data_size = Some(bytes);
+        self
+    }
+
+    /// Starts the compute meter of the transaction at `units` instead of the default
+    /// limit, by adding a compute budget instruction next to the one of
+    /// `max_loaded_accounts_data_size`. The compute budget instructions are executed
+    /// first and consume units from the same meter. Values above the runtime's maximum
+    /// limit are capped by the runtime.
+    pub fn initial_compute_units(&mut self, units: u64) -> &mut Self {
+        self.initial_compute_units = Some(units);
+        self
+    }
+
+    /// Same as `initial_compute_units`, with the type of the compute budget instruction.
+    pub fn compute_unit_limit(&mut self, units: u32) -> &mut Self {
+        self.initial_compute_units = Some(units.into());
+        self
+    }
+
+    /// Requests a prioritization fee of `micro_lamports` per compute unit of the limit by
+    /// adding a compute budget instruction. The fee is included in
+    /// `FeePayerInfo::required_fee`.
+    pub fn compute_unit_price(&mut self, micro_lamports: u64) -> &mut Self {
+        self.compute_unit_price = Some(micro_lamports);
+        self
+    }
+
+    /// Stores an address lookup table holding `addresses` at `table` in the bank. With
+    /// `v0_message` built messages look up their non-signer accounts in the registered
+    /// tables, resolved against the table accounts in the bank.
+    pub fn address_lookup_table(&mut self, table: Pubkey, addresses: Vec<Pubkey>) -> &mut Self {
+        self.address_lookup_tables.push((table, addresses));
+        self
+    }
+
+    /// Records the instructions invoked through CPI in the `inner_instructions` of the
+    /// execution details.
+    pub fn record_cpi(&mut self, enable: bool) -> &mut Self {
+        self.record_cpi = enable;
+        self
+    }
+
+    /// Loads and verifies the program before deploying it, the way the loader does on
+    /// deployment. A rejected program fails the build with the loader's diagnostic,
+    /// e.g. an invalid instruction or relocation, instead of failing at execution. On by
+    /// default, disabling it deploys the program as is.
+    pub fn verify_program(&mut self, value: bool) -> &mut Self {
+        self.verify_program = value;
+        self
+    }
+
+    /// Deploys the program once at `program_id` instead of a generated id and keeps using
+    /// it for following builds. A program already deployed at the id is replaced, the
+    /// build fails if any other account occupies it.
+    pub fn program_id(&mut self, program_id: Pubkey) -> &mut Self {
+        self.program_id = Some(program_id);
+        self
+    }
+
+    /// Sorts the non-fee-payer, non-signer accounts of the built message by pubkey, so
+    /// the serialized transaction is stable for golden-file comparisons. This is for test
+    /// stability only and may not match the ordering of a specific wallet. Messages built
+    /// from `raw_instructions` are always ordered by pubkey within each account group.
+    pub fn canonical_account_order(&mut self, value: bool) -> &mut Self {
+        self.tx_builder.canonical_account_order(value);
+        self
+    }
+
+    /// Instruments the syscalls of the execution environment to tally their invocations,
+    /// see `last_build_syscall_counts`. Off by default to avoid the overhead. A processor
+    /// set with `tx_processor` is not instrumented.
+    pub fn count_syscalls(&mut self, value: bool) -> &mut Self {
+        if self.count_syscalls != value {
+            // the environment is chosen when the processor is created
+            self.tx_processor = None;
+        }
+        self.count_syscalls = value;
+        self
+    }
+
+    /// Invocations of every syscall by the last executed transaction, keyed by syscall
+    /// name. Empty unless `count_syscalls` is enabled.
+    pub fn last_build_syscall_counts(&self) -> HashMap<String, u64> {
+        self.last_syscall_counts.clone()
+    }
+
+    /// Pubkeys that have to sign the last built transaction: the fee payer followed by
+    /// the signer accounts, deduplicated, in the order of the compiled message.
+    pub fn required_signers(&self) -> Vec<Pubkey> {
+        self.required_signers.clone()
+    }
+
+    /// Program cache statistics of the last executed transaction. A warm build only
+    /// has hits, while a cold one misses the program and has to load it.
+    pub fn last_build_cache_stats(&self) -> CacheStats {
+        self.last_cache_stats
+    }
+
+    /// Result, logs, return data and consumed compute units of the last transaction
+    /// executed by a build, `None` before the first build.
+    pub fn last_execution(&self) -> Option<ExecutionSummary> {
+        self.last_execution.clone()
+    }
+
+    /// State of the account after the last build, whatever the bank type: the state left
+    /// by the execution if it modified the account, the state in the bank otherwise.
+    /// Unlike the bank, it reflects builds that are not committed.
+    pub fn account_after(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.last_accounts
+            .get(pubkey)
+            .cloned()
+            .or_else(|| self.bank.get_account_shared_data(pubkey))
+    }
+
+    /// Current state of the ProgramData account of the program deployed at a fixed id or
+    /// as `upgradeable`, e.g. to check the result of `upgrade_program`. `None` for programs
+    /// deployed through the bank, which cannot be upgraded by the builder.
+    pub fn program_data_after(&self) -> Option<ProgramDataInfo> {
+        let program_id = self.deployed_program_id?;
+        let program = self.bank.get_account_shared_data(&program_id)?;
+        let UpgradeableLoaderState::Program {
+            programdata_address,
+        } = bincode::deserialize(program.data()).ok()?
+        else {
+            return None;
+        };
+        let programdata = self.bank.get_account_shared_data(&programdata_address)?;
+        let UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        } = bincode::deserialize(programdata.data()).ok()?
+        else {
+            return None;
+        };
+        Some(ProgramDataInfo {
+            program_id,
+            programdata_address,
+            slot,
+            upgrade_authority: upgrade_authority_address,
+            program_len: programdata
+                .data()
+                .len()
+                .saturating_sub(UpgradeableLoaderState::size_of_programdata_metadata()),
+        })
+    }
+
+    /// Id of the program deployed once with an upgrade authority or a fixed program id.
+    pub fn get_program_id(&self) -> Option<Pubkey> {
+        self.deployed_program_id
+    }
+
+    /// Withdraws the balance of every created fee payer from the existing account
+    /// `source` instead of creating the lamports, so the total supply is preserved.
+    pub fn funding_source(&mut self, source: Option<Pubkey>) -> &mut Self {
+        self.funding_source = source;
+        self
+    }
+
+    /// Uses `pubkey` as the fee payer of every build instead of creating new ones. It is
+    /// funded with `Settings::fee_payer_balance` only if the account does not exist yet.
+    /// Takes precedence over `fee_payer_seed`.
+    pub fn fee_payer(&mut self, pubkey: Pubkey) -> &mut Self {
+        self.fee_payer = Some(pubkey);
+        self
+    }
+
+    /// Derives fee payers from `base` and the number of fee payers created so far
+    /// instead of generating random ones, which makes runs reproducible.
+    pub fn fee_payer_seed(&mut self, base: Option<Pubkey>) -> &mut Self {
+        self.fee_payer_seed = base;
+        self.fee_payer_count = 0;
+        self
+    }
+
+    /// Leaves the sysvar out of the sysvar cache of the processor created by the next
+    /// build. It has no effect on a processor that already exists.
+    pub fn skip_sysvar(&mut self, sysvar: Pubkey) -> &mut Self {
+        self.skipped_sysvars.push(sysvar);
+        self
+    }
+
+    /// Registers only `set` with the processor created by the next build instead of all
+    /// builtins, e.g. to test how a program copes with a missing builtin. It drops the
+    /// current processor and has no effect on a processor set with `tx_processor`.
+    pub fn builtins(&mut self, set: BuiltinSet) -> &mut Self {
+        self.builtins = set;
+        self.tx_processor = None;
+        self
+    }
+
+    /// Registers a callback invoked with the index and execution result of every
+    /// transaction of a build, in submission order. It runs synchronously within the
+    /// build, after the batch has been executed by the processor.
+    pub fn on_transaction(&mut self, callback: TransactionCallback) -> &mut Self {
+        self.on_transaction = Some(callback);
+        self
+    }
+
+    /// Executes the given instructions as they are instead of a single instruction of the
+    /// deployed program. The account metas of `account` only seed the bank in this mode,
+    /// the accounts of the transaction are derived from the instructions.
+    pub fn raw_instructions(&mut self, instructions: Vec<Instruction>) -> &mut Self {
+        self.raw_instructions = Some(instructions);
+        self
+    }
+
     pub fn account(&mut self, meta: AccountMeta, account: Option<AccountSharedData>) -> &mut Self {
         self.accounts.push((meta, account));
         self
     }
 
+    /// Bounds the logs captured per transaction to `bytes`, see
+    /// [`BuildOutput::logs_truncated`].
+    pub fn max_log_bytes(&mut self, bytes: usize) -> &mut Self {
+        self.max_log_bytes = Some(bytes);
+        self
+    }
+
+    /// Bounds the return data captured per transaction to `bytes`, see
+    /// [`BuildOutput::return_data_truncated`].
+    pub fn max_return_data_bytes(&mut self, bytes: usize) -> &mut Self {
+        self.max_return_data_bytes = Some(bytes);
+        self
+    }
+
+    /// Builds one transaction of the program per group instead of a single one from
+    /// `calldata` and `account`, executed in order in the same batch. Later transactions
+    /// observe the writable accounts of earlier successful ones. The output holds one
+    /// result per transaction, only `build` supports multiple transactions.
+    pub fn transactions(&mut self, groups: Vec<TransactionGroup>) -> &mut Self {
+        self.transactions = Some(groups);
+        self
+    }
+
+    /// Records the writable accounts after every instruction of the transaction, see
+    /// `BuildOutput::intermediate_states`. Every instruction prefix of the transaction is
+    /// executed once more, along with its compute budget and nonce instructions, so builds
+    /// get slower with the number of instructions. A prefix sees fewer instructions than
+    /// the transaction, e.g. without a compute unit limit its default limit is lower and
+    /// the instructions sysvar lists only the prefix. Builds of multiple transactions are
+    /// not recorded.
+    pub fn record_intermediate_state(&mut self, value: bool) -> &mut Self {
+        self.record_intermediate_state = value;
+        self
+    }
+
+    /// Records the compute units consumed by every instruction of the first transaction,
+    /// builtins included, from the logs of its execution, see
+    /// `BuildOutput::per_instruction_units`.
+    pub fn record_instruction_units(&mut self, value: bool) -> &mut Self {
+        self.record_instruction_units = value;
+        self
+    }
+
+    /// Makes programs fail with `ReturnDataTooLarge` when setting more than `bytes` bytes
+    /// of return data, instead of the runtime's limit of `MAX_RETURN_DATA` bytes. Caps
+    /// above the runtime's limit have no effect. Unlike `max_return_data_bytes`, this
+    /// changes the outcome of the execution.
+    pub fn return_data_cap(&mut self, bytes: usize) -> &mut Self {
+        self.return_data_cap = Some(bytes);
+        self
+    }
+
+    /// Makes `build_batch` run up to `max_passes` passes over the batch, every pass
+    /// executing again the transactions that failed to load because an account was not
+    /// present yet, e.g. one created by a later transaction of the batch. Passes stop
+    /// early once no transaction is left to retry or a pass commits no transaction, so
+    /// the batch terminates after at most `max_passes` passes.
+    pub fn retry_on_missing_account(&mut self, max_passes: usize) -> &mut Self {
+        self.max_batch_passes = max_passes;
+        self
+    }
+
+    /// Adds the accounts described in the compact format of [`parse_account_metas`].
+    pub fn accounts_from_dsl(&mut self, dsl: &str) -> Result<&mut Self> {
+        for meta in parse_account_metas(dsl)? {
+            self.account(meta, None);
+        }
+        Ok(self)
+    }
+
     pub fn account_with_balance(
         &mut self,
         pubkey: Pubkey,
@@ -244,19 +1408,103 @@ where
         )
     }
 
+    /// Executes programs at the given slot, epoch and time instead of the bank's, e.g. to
+    /// exercise programs reading `Clock::unix_timestamp`. The values make up the Clock
+    /// sysvar written to the bank and are the slot and epoch of the processor. `slot` has
+    /// to be after `DEPLOYMENT_SLOT`, deployed programs are not visible before.
+    ///
+    /// The processor fills its sysvar cache with `fill_missing_sysvar_cache_entries` once,
+    /// when it is created, and never refreshes it afterwards. So the clock drops the
+    /// current processor and the next build creates one that caches the new Clock. It has
+    /// no effect on a processor set with `tx_processor`, and a Clock left out with
+    /// `skip_sysvar` stays missing from the cache.
+    pub fn clock(&mut self, slot: Slot, epoch: Epoch, unix_timestamp: UnixTimestamp) -> &mut Self {
+        self.sysvars.clock = Some(Clock {
+            slot,
+            epoch_start_timestamp: unix_timestamp,
+            epoch,
+            leader_schedule_epoch: epoch,
+            unix_timestamp,
+        });
+        self.tx_processor = None;
+        self
+    }
+
+    /// Executes programs with `rent` instead of the bank's Rent sysvar, e.g. to exercise
+    /// rent exemption. It is written to the bank and cached by the processor, so it is
+    /// what `Rent::get` returns, and accounts left below its exemption threshold fail the
+    /// transaction with `InsufficientFundsForRent`. Like `clock`, it drops the current
+    /// processor and has no effect on a processor set with `tx_processor`.
+    pub fn rent(&mut self, rent: Rent) -> &mut Self {
+        self.rent_collector = Some(RentCollector {
+            rent: rent.clone(),
+            ..Default::default()
+        });
+        self.sysvars.rent = Some(rent);
+        self.tx_processor = None;
+        self
+    }
+
+    /// Makes single-transaction builds use the durable nonce stored in the nonce account
+    /// `address` as their blockhash, advanced by `authority` in a system instruction
+    /// prepended to the transaction. The nonce account is created unless it exists, along
+    /// with the RecentBlockhashes sysvar the system program needs to advance it. The
+    /// default check result carries the nonce account, and the bank is left as is, so
+    /// every build uses the same nonce.
+    pub fn nonce_account(&mut self, address: Pubkey, authority: Pubkey) -> &mut Self {
+        self.nonce_account = Some((address, authority));
+        // the sysvar cache of a processor created without RecentBlockhashes lacks it
+        self.tx_processor = None;
+        self
+    }
+
+    /// Makes builds fail before executing anything unless the account `pubkey` is owned
+    /// by `owner`, which catches accounts seeded with the wrong owner. Every mismatch is
+    /// listed in the returned `BuilderError`, a missing account is a mismatch as well.
+    pub fn expect_owner(&mut self, pubkey: Pubkey, owner: Pubkey) -> &mut Self {
+        self.expected_owners.push((pubkey, owner));
+        self
+    }
+
+    /// Builds the transaction with a blockhash outside the valid window of the bank and
+    /// makes the blockhash check fail as the bank would for an expired transaction, so
+    /// the transaction is not executed and fails with `BlockhashNotFound`. An explicit
+    /// `check_result` takes precedence over the expiration.
+    pub fn expired_blockhash(&mut self, value: bool) -> &mut Self {
+        self.expired_blockhash = value;
+        self
+    }
+
+    /// Charges `value` lamports per signature instead of the default fee structure's,
+    /// e.g. to test fee deduction. Zero makes transactions fee-free. It only applies to
+    /// the default check result, an explicit `check_result` takes precedence and is
+    /// charged with the default fee structure.
+    pub fn lamports_per_signature(&mut self, value: u64) -> &mut Self {
+        self.fee_structure = Some(FeeStructure {
+            lamports_per_signature: value,
+            ..Default::default()
+        });
+        self
+    }
+
     pub fn check_result(&mut self, result: TransactionCheckResult) -> &mut Self {
         self.check_result = Some(result);
         self
     }
 
     fn prepare_accounts(&mut self) -> Result<ExecutionAccounts> {
+        self.prepare_accounts_from(self.accounts.clone())
+    }
+
+    fn prepare_accounts_from(
+        &mut self,
+        metas: Vec<(AccountMeta, Option<AccountSharedData>)>,
+    ) -> Result<ExecutionAccounts> {
         let mut accounts = vec![];
         let mut signatures = HashMap::new();
-        for (meta, account) in self.accounts.iter() {
+        for (meta, account) in metas {
             if let Some(account) = account {
-                self.bank
-                    .insert_account(meta.pubkey, account.clone())
-                    .map_err(|e| Error::BuilderError(e.to_string()))?;
+                self.store_account(meta.pubkey, account.clone())?;
             }
 
             accounts.push(meta.clone());
@@ -273,25 +1521,497 @@ where
         })
     }
 
+    /// Creates the nonce account and the RecentBlockhashes sysvar if needed and returns
+    /// the instruction advancing the nonce.
+    #[allow(deprecated)]
+    fn prepare_nonce_account(&mut self) -> Result<Option<Instruction>> {
+        use solana_sdk::sysvar::recent_blockhashes::{IterItem, RecentBlockhashes};
+
+        let Some((address, authority)) = self.nonce_account else {
+            return Ok(None);
+        };
+        let lamports_per_signature = self.fee_structure().lamports_per_signature;
+        if self.durable_nonce().is_none() {
+            if self.load_account(&address).is_some() {
+                return Err(Error::BuilderError(format!(
+                    "Account {address} is not an initialized nonce account"
+                )));
+            }
+            let data = nonce::state::Data::new(
+                authority,
+                DurableNonce::from_blockhash(&Hash::new_unique()),
+                lamports_per_signature,
+            );
+            let account = AccountSharedData::new_data(
+                self.rent_sysvar().minimum_balance(nonce::State::size()),
+                &nonce::state::Versions::new(nonce::State::Initialized(data)),
+                &system_program::id(),
+            )
+            .map_err(|e| Error::BuilderError(e.to_string()))?;
+            self.store_account(address, account)?;
+        }
+        if self.load_account(&RecentBlockhashes::id()).is_none() {
+            let recent_blockhashes = RecentBlockhashes::from_iter([IterItem(
+                0,
+                &self.bank.last_blockhash(),
+                lamports_per_signature,
+            )]);
+            self.store_account(RecentBlockhashes::id(), sysvar_account(&recent_blockhashes))?;
+        }
+        Ok(Some(system_instruction::advance_nonce_account(
+            &address, &authority,
+        )))
+    }
+
+    /// Durable nonce stored in the nonce account, if it is initialized.
+    fn durable_nonce(&self) -> Option<Hash> {
+        let (address, _) = self.nonce_account?;
+        let account = self.load_account(&address)?;
+        let versions: nonce::state::Versions = bincode::deserialize(account.data()).ok()?;
+        match versions.state() {
+            nonce::State::Initialized(data) => Some(data.blockhash()),
+            nonce::State::Uninitialized => None,
+        }
+    }
+
+    fn verify_owners(&self) -> Result<()> {
+        let mismatches = self
+            .expected_owners
+            .iter()
+            .filter_map(|(pubkey, expected)| match self.load_account(pubkey) {
+                Some(account) if account.owner() == expected => None,
+                Some(account) => Some(format!(
+                    "{pubkey} is owned by {}, expected {expected}",
+                    account.owner()
+                )),
+                None => Some(format!(
+                    "{pubkey} does not exist, expected owner {expected}"
+                )),
+            })
+            .collect::<Vec<_>>();
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+        Err(Error::BuilderError(format!(
+            "Unexpected account owners: {}",
+            mismatches.join("; ")
+        )))
+    }
+
+    fn deploy(&mut self) -> Result<Pubkey> {
+        let deploy_once = self.upgradeable;
+        if !deploy_once && self.program_id.is_none() && self.reuse_programs {
+            let buffer = self.read_verified_program()?;
+            let program_id = Pubkey::new_from_array(hash(&buffer).to_bytes());
+            if !self
+                .load_account(&program_id)
+                .is_some_and(|account| is_program(&account))
+            {
+                // the builtins of the shared processor may be registered with another bank
+                register_builtin_accounts(&self.bank);
+                self.deploy_upgradeable_program(buffer, program_id, None)?;
+            }
+            return Ok(program_id);
+        }
+        if !deploy_once && self.program_id.is_none() {
+            let buffer = self.read_verified_program()?;
+            if self.staged_accounts.is_some() {
+                let program_id = Pubkey::new_unique();
+                self.deploy_upgradeable_program(buffer, program_id, None)?;
+                return Ok(program_id);
+            }
+            return self
+                .bank
+                .deploy_program(buffer)
+                .map_err(|e| Error::BuilderError(e.to_string()));
+        }
+
+        if let Some(program_id) = self.deployed_program_id {
+            if self.program_id.map_or(true, |id| id == program_id) {
+                return Ok(program_id);
+            }
+        }
+        let program_id = match self.program_id {
+            Some(program_id) => {
+                // a program left at the id, e.g. by another builder on the same bank, is
+                // replaced by the deployment
+                let occupied = self
+                    .load_account(&program_id)
+                    .is_some_and(|account| account.lamports() > 0 && !is_program(&account));
+                if occupied {
+                    return Err(Error::BuilderError(format!(
+                        "Program id {program_id} is already occupied by another account"
+                    )));
+                }
+                program_id
+            }
+            None => Pubkey::new_unique(),
+        };
+        let buffer = self.read_verified_program()?;
+        self.deploy_upgradeable_program(buffer, program_id, self.upgrade_authority)?;
+        self.deployed_program_id = Some(program_id);
+        Ok(program_id)
+    }
+
+    fn deploy_upgradeable_program(
+        &mut self,
+        buffer: Vec<u8>,
+        program_id: Pubkey,
+        authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let rent = self.rent_sysvar();
+        let programdata_address = Pubkey::new_unique();
+
+        let mut program_account = AccountSharedData::new_data(
+            rent.minimum_balance(UpgradeableLoaderState::size_of_program()),
+            &UpgradeableLoaderState::Program {
+                programdata_address,
+            },
+            &bpf_loader_upgradeable::id(),
+        )
+        .map_err(|e| Error::BuilderError(e.to_string()))?;
+        program_account.set_executable(true);
+
+        // leave room for upgrades like `solana program deploy` does
+        let programdata_len =
+            UpgradeableLoaderState::size_of_programdata(buffer.len().saturating_mul(2));
+        let mut programdata_account = AccountSharedData::new(
+            rent.minimum_balance(programdata_len),
+            programdata_len,
+            &bpf_loader_upgradeable::id(),
+        );
+        let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+        let header = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+            slot: DEPLOYMENT_SLOT,
+            upgrade_authority_address: authority,
+        })
+        .map_err(|e| Error::BuilderError(e.to_string()))?;
+        let data = programdata_account.data_as_mut_slice();
+        data[..header.len()].copy_from_slice(&header);
+        data[metadata_len..metadata_len + buffer.len()].copy_from_slice(&buffer);
+
+        self.store_account(program_id, program_account)?;
+        self.store_account(programdata_address, programdata_account)?;
+        // a program compiled from the previous buffer at the same id and deployment slot
+        // would be served from the cache otherwise
+        if let Some(tx_processor) = self.tx_processor.as_ref() {
+            tx_processor
+                .program_cache
+                .write()
+                .map_err(|e| Error::BuilderError(e.to_string()))?
+                .remove_programs(std::iter::once(program_id));
+        }
+        Ok(())
+    }
+
+    fn compute_budget_instructions(&self) -> Vec<Instruction> {
+        // every kind of compute budget instruction may appear only once per transaction
+        let compute_unit_limit = self.initial_compute_units.map(|units| {
+            ComputeBudgetInstruction::set_compute_unit_limit(
+                u32::try_from(units).unwrap_or(u32::MAX),
+            )
+        });
+        let compute_unit_price = self
+            .compute_unit_price
+            .map(ComputeBudgetInstruction::set_compute_unit_price);
+        let loaded_accounts_data_size_limit = self
+            .max_loaded_accounts_data_size
+            .map(ComputeBudgetInstruction::set_loaded_accounts_data_size_limit);
+        compute_unit_limit
+            .into_iter()
+            .chain(compute_unit_price)
+            .chain(loaded_accounts_data_size_limit)
+            .collect()
+    }
+
+    fn notify_transactions(&mut self, output: &LoadAndExecuteSanitizedTransactionsOutput) {
+        if let Some(callback) = self.on_transaction.as_mut() {
+            for (index, result) in output.execution_results.iter().enumerate() {
+                callback(index, result);
+            }
+        }
+    }
+
+    fn fee_payer_info(
+        &self,
+        transaction: &SanitizedTransaction,
+        check_result: &TransactionCheckResult,
+    ) -> FeePayerInfo {
+        let message = transaction.message();
+        let fee_payer = *message.fee_payer();
+        // signature fees of the default fee structure plus the requested prioritization
+        // fee. Precompile instructions are charged per verified signature on top of the
+        // transaction signatures, the same for legacy and v0 messages.
+        let required_fee = match check_result {
+            Ok(details) if details.lamports_per_signature > 0 => {
+                let signatures = message.get_signature_details();
+                let prioritization_fee =
+                    process_compute_budget_instructions(message.program_instructions_iter())
+                        .map_or(0, |limits| FeeBudgetLimits::from(limits).prioritization_fee);
+                self.fee_structure().lamports_per_signature
+                    * signatures
+                        .num_transaction_signatures()
+                        .saturating_add(signatures.num_secp256k1_instruction_signatures())
+                        .saturating_add(signatures.num_ed25519_instruction_signatures())
+                    + prioritization_fee
+            }
+            _ => 0,
+        };
+        FeePayerInfo {
+            fee_payer,
+            balance: self
+                .load_account(&fee_payer)
+                .map_or(0, |account| account.lamports()),
+            required_fee,
+        }
+    }
+
+    fn ensure_tx_processor(&mut self) -> Result<()> {
+        if self.tx_processor.is_none() {
+            if let Some(clock) = self.sysvars.clock.as_ref() {
+                if clock.slot <= DEPLOYMENT_SLOT {
+                    return Err(Error::BuilderError(format!(
+                        "Clock slot {} is not after the deployment slot {DEPLOYMENT_SLOT}",
+                        clock.slot
+                    )));
+                }
+            }
+            let tx_processor = create_transaction_processor_with_sysvars(
+                &mut self.bank,
+                self.fork_graph.clone(),
+                &self.skipped_sysvars,
+                &self.sysvars,
+                &self.builtins,
+            )?;
+            // keep the accounts in line with the cached sysvars
+            for (pubkey, account) in self.sysvars.accounts() {
+                self.bank
+                    .insert_account(pubkey, account)
+                    .map_err(|e| Error::BuilderError(e.to_string()))?;
+            }
+            if self.count_syscalls {
+                tx_processor
+                    .program_cache
+                    .write()
+                    .map_err(|e| Error::BuilderError(e.to_string()))?
+                    .environments
+                    .program_runtime_v1 = Arc::new(create_custom_environment_ex(true));
+            }
+            self.tx_processor = Some(Arc::new(tx_processor));
+        }
+        Ok(())
+    }
+
+    /// Cumulative statistics of the program cache of the processor.
+    fn cache_stats(&self) -> Result<CacheStats> {
+        let program_cache = self
+            .tx_processor
+            .as_ref()
+            .ok_or(Error::TransactionProcessorIsNone)?
+            .program_cache
+            .read()
+            .map_err(|e| Error::BuilderError(e.to_string()))?;
+        let stats = &program_cache.stats;
+        Ok(CacheStats {
+            hits: stats.hits.load(Ordering::Relaxed),
+            misses: stats.misses.load(Ordering::Relaxed),
+            recompiles: stats.reloads.load(Ordering::Relaxed),
+        })
+    }
+
+    fn register_lookup_tables(&mut self) -> Result<()> {
+        let rent = self.rent_sysvar();
+        let mut tables = vec![];
+        for (key, addresses) in self.address_lookup_tables.clone() {
+            let mut data = vec![0; LOOKUP_TABLE_META_SIZE];
+            AddressLookupTable::overwrite_meta_data(&mut data, LookupTableMeta::default())
+                .map_err(|e| Error::BuilderError(e.to_string()))?;
+            data.extend(addresses.iter().flat_map(|address| address.to_bytes()));
+            let mut account = AccountSharedData::new(
+                rent.minimum_balance(data.len()),
+                0,
+                &address_lookup_table::program::id(),
+            );
+            account.set_data(data);
+            self.store_account(key, account)?;
+
+            let account = self
+                .load_account(&key)
+                .ok_or(Error::BuilderError(format!("Lookup table {key} not found")))?;
+            let table = AddressLookupTable::deserialize(account.data())
+                .map_err(|e| Error::BuilderError(e.to_string()))?;
+            tables.push(AddressLookupTableAccount {
+                key,
+                addresses: table.addresses.to_vec(),
+            });
+        }
+        self.tx_builder.address_lookup_tables(tables);
+        Ok(())
+    }
+
+    pub(crate) fn rent_sysvar(&self) -> Rent {
+        self.load_account(&Rent::id())
+            .and_then(|account| bincode::deserialize(account.data()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes `account` to the bank, or to the staged accounts while simulating.
+    fn store_account(&mut self, pubkey: Pubkey, account: AccountSharedData) -> Result<()> {
+        match self.staged_accounts.as_mut() {
+            Some(staged) => {
+                staged.insert(pubkey, account);
+                Ok(())
+            }
+            None => self
+                .bank
+                .insert_account(pubkey, account)
+                .map_err(|e| Error::BuilderError(e.to_string())),
+        }
+    }
+
+    /// Account as the build sees it, the staged accounts take precedence over the bank.
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.staged_accounts
+            .as_ref()
+            .and_then(|staged| staged.get(pubkey).cloned())
+            .or_else(|| self.bank.get_account_shared_data(pubkey))
+    }
+
+    /// Advances the bank to the next slot, a simulation keeps the slot.
+    fn bump_bank(&mut self) -> Result<()> {
+        if self.staged_accounts.is_some() {
+            return Ok(());
+        }
+        self.bank
+            .bump()
+            .map_err(|e| Error::BuilderError(e.to_string()))
+    }
+
+    fn snapshot_readonly_accounts(
+        &self,
+        keys: &[Pubkey],
+    ) -> Option<Vec<(Pubkey, AccountSharedData)>> {
+        if !self.strict_readonly {
+            return None;
+        }
+
+        Some(
+            keys.iter()
+                .map(|key| (*key, self.load_account(key).unwrap_or_default()))
+                .collect(),
+        )
+    }
+
     fn get_checked_tx_details(&self) -> TransactionCheckResult {
+        if self.expired_blockhash && self.check_result.is_none() {
+            return Err(TransactionError::BlockhashNotFound);
+        }
         self.check_result
             .clone()
             .unwrap_or(Ok(CheckedTransactionDetails {
-                nonce: None,
-                lamports_per_signature: 20,
+                nonce: self.nonce_account.and_then(|(address, _)| {
+                    let account = self.load_account(&address)?;
+                    Some(NoncePartial::new(address, account))
+                }),
+                lamports_per_signature: self
+                    .fee_structure
+                    .as_ref()
+                    .map_or(20, |fee_structure| fee_structure.lamports_per_signature),
             }))
     }
 
+    /// Fee structure charged by the processor, see `lamports_per_signature`.
+    fn fee_structure(&self) -> &FeeStructure {
+        static DEFAULT: OnceLock<FeeStructure> = OnceLock::new();
+        self.fee_structure
+            .as_ref()
+            .filter(|_| self.check_result.is_none())
+            .unwrap_or_else(|| DEFAULT.get_or_init(FeeStructure::default))
+    }
+
+    fn recent_blockhash(&self) -> Hash {
+        if let Some(durable_nonce) = self.durable_nonce() {
+            return durable_nonce;
+        }
+        if self.expired_blockhash {
+            // never recorded by the bank, so older than any valid blockhash
+            Hash::new_unique()
+        } else {
+            self.bank.last_blockhash()
+        }
+    }
+
     fn create_fee_payer(&mut self) -> Result<Pubkey> {
-        let fee_payer = Pubkey::new_unique();
+        if let Some(fee_payer) = self.fee_payer {
+            let exists = self
+                .load_account(&fee_payer)
+                .is_some_and(|account| account.lamports() > 0);
+            if exists {
+                return Ok(fee_payer);
+            }
+        }
+        let fee_payer = match (self.fee_payer, self.fee_payer_seed) {
+            (Some(fee_payer), _) => fee_payer,
+            (None, Some(base)) => {
+                let index = self.fee_payer_count;
+                self.fee_payer_count += 1;
+                Pubkey::create_with_seed(&base, &index.to_string(), &system_program::id())
+                    .map_err(|e| Error::BuilderError(e.to_string()))?
+            }
+            (None, None) => Pubkey::new_unique(),
+        };
+        if let Some(source) = self.funding_source {
+            self.withdraw(&source, self.settings.fee_payer_balance)?;
+        }
         let mut account_data = AccountSharedData::default();
         account_data.set_lamports(self.settings.fee_payer_balance);
-        self.bank
-            .insert_account(fee_payer, account_data)
-            .map_err(|e| Error::BuilderError(e.to_string()))?;
+        self.store_account(fee_payer, account_data)?;
         Ok(fee_payer)
     }
 
+    fn withdraw(&mut self, source: &Pubkey, lamports: u64) -> Result<()> {
+        let mut account = self.load_account(source).ok_or_else(|| {
+            Error::BuilderError(format!("Funding source {source} does not exist"))
+        })?;
+        let balance = account.lamports().checked_sub(lamports).ok_or_else(|| {
+            Error::BuilderError(format!(
+                "Funding source {source} can not pay {lamports} lamports, balance {}",
+                account.lamports()
+            ))
+        })?;
+        account.set_lamports(balance);
+        self.store_account(*source, account)
+    }
+
+    fn read_verified_program(&mut self) -> Result<Vec<u8>> {
+        let buffer = self.read_program()?;
+        if self.verify_program {
+            self.verify_elf(&buffer)?;
+        }
+        Ok(buffer)
+    }
+
+    /// Loads and verifies `buffer` like the loader does on deployment, with the program
+    /// runtime environment of the processor.
+    fn verify_elf(&mut self, buffer: &[u8]) -> Result<()> {
+        self.ensure_tx_processor()?;
+        let environment = self
+            .tx_processor
+            .as_ref()
+            .ok_or(Error::TransactionProcessorIsNone)?
+            .program_cache
+            .read()
+            .map_err(|e| Error::BuilderError(e.to_string()))?
+            .environments
+            .program_runtime_v1
+            .clone();
+        let executable = Executable::<InvokeContext>::from_elf(buffer, environment)
+            .map_err(|e| Error::BuilderError(format!("Failed to load program ELF: {e}")))?;
+        executable
+            .verify::<RequisiteVerifier>()
+            .map_err(|e| Error::BuilderError(format!("Failed to verify program ELF: {e}")))
+    }
+
     fn read_program(&self) -> Result<Vec<u8>> {
         if self.program_buffer.is_some() && self.program_path.is_some() {
             return Err(Error::BuilderError(
@@ -316,28 +2036,155 @@ where
         Ok(buffer)
     }
 
+    fn get_processing_environment(&self) -> TransactionProcessingEnvironment {
+        TransactionProcessingEnvironment {
+            feature_set: self.feature_set.clone(),
+            rent_collector: self.rent_collector.as_ref(),
+            fee_structure: Some(self.fee_structure()),
+            ..Default::default()
+        }
+    }
+
     fn get_processing_config(&self) -> TransactionProcessingConfig {
         TransactionProcessingConfig {
             recording_config: ExecutionRecordingConfig {
                 enable_log_recording: true,
                 enable_return_data_recording: true,
-                enable_cpi_recording: false,
+                enable_cpi_recording: self.record_cpi,
             },
             ..Default::default()
         }
     }
 }
 
-pub fn create_transaction_processor<B>(
+fn is_missing_account(output: &BuildOutput) -> bool {
+    matches!(
+        output.execution_results.first(),
+        Some(TransactionExecutionResult::NotExecuted(
+            TransactionError::AccountNotFound | TransactionError::ProgramAccountNotFound
+        ))
+    )
+}
+
+fn is_program(account: &AccountSharedData) -> bool {
+    account.executable()
+        && [
+            bpf_loader_upgradeable::id(),
+            bpf_loader::id(),
+            bpf_loader_deprecated::id(),
+        ]
+        .contains(account.owner())
+}
+
+fn writable_keys(transaction: &SanitizedTransaction) -> Vec<Pubkey> {
+    let message = transaction.message();
+    message
+        .account_keys()
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| message.is_writable(*index))
+        .map(|(_, key)| *key)
+        .collect()
+}
+
+fn is_vote_transaction(tx: &VersionedTransaction) -> bool {
+    let keys = tx.message.static_account_keys();
+    tx.message
+        .instructions()
+        .iter()
+        .any(|instruction| vote::program::check_id(instruction.program_id(keys)))
+}
+
+fn readonly_keys(metas: &[AccountMeta]) -> Vec<Pubkey> {
+    metas
+        .iter()
+        .filter(|meta| !meta.is_writable)
+        .map(|meta| meta.pubkey)
+        .collect()
+}
+
+fn verify_readonly_accounts(
+    snapshot: &[(Pubkey, AccountSharedData)],
+    output: &LoadAndExecuteSanitizedTransactionsOutput,
+) -> Result<()> {
+    let mut modified = vec![];
+    for loaded in output.loaded_transactions.iter().flatten() {
+        for (pubkey, before) in snapshot {
+            let changed = loaded
+                .accounts
+                .iter()
+                .find(|(key, _)| key == pubkey)
+                .is_some_and(|(_, after)| !accounts_equal(before, after));
+            if changed && !modified.contains(pubkey) {
+                modified.push(*pubkey);
+            }
+        }
+    }
+
+    if modified.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::BuilderError(format!(
+            "Read-only accounts modified during execution: {}",
+            modified
+                .iter()
+                .map(|key| key.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )))
+    }
+}
+
+pub fn create_transaction_processor<B, FG>(
+    bank: &mut B,
+    fork_graph: Arc<RwLock<FG>>,
+) -> Result<TransactionBatchProcessor<FG>>
+where
+    B: TransactionProcessingCallback + BankOperations + BankInfo<Slot = Slot>,
+    FG: ForkGraph,
+{
+    create_transaction_processor_ex(bank, fork_graph, &[])
+}
+
+/// Same as `create_transaction_processor_with_sysvars` without overridden sysvars.
+pub fn create_transaction_processor_ex<B, FG>(
+    bank: &mut B,
+    fork_graph: Arc<RwLock<FG>>,
+    skipped_sysvars: &[Pubkey],
+) -> Result<TransactionBatchProcessor<FG>>
+where
+    B: TransactionProcessingCallback + BankOperations + BankInfo<Slot = Slot>,
+    FG: ForkGraph,
+{
+    create_transaction_processor_with_sysvars(
+        bank,
+        fork_graph,
+        skipped_sysvars,
+        &SysvarOverrides::default(),
+        &BuiltinSet::all(),
+    )
+}
+
+/// Same as `create_transaction_processor`, but leaves the given sysvars out of the
+/// sysvar cache, so programs run into the missing sysvar path when reading them. The
+/// overridden `sysvars` are cached instead of the ones written by `set_clock`, and the
+/// processor runs at the slot and epoch of an overridden Clock. The bank's sysvar
+/// accounts are left as they are. Only `builtins` are registered.
+pub fn create_transaction_processor_with_sysvars<B, FG>(
     bank: &mut B,
-    fork_graph: Arc<RwLock<MockForkGraph>>,
-) -> Result<TransactionBatchProcessor<MockForkGraph>>
+    fork_graph: Arc<RwLock<FG>>,
+    skipped_sysvars: &[Pubkey],
+    sysvars: &SysvarOverrides,
+    builtins: &BuiltinSet,
+) -> Result<TransactionBatchProcessor<FG>>
 where
     B: TransactionProcessingCallback + BankOperations + BankInfo<Slot = Slot>,
+    FG: ForkGraph,
 {
-    let tx_processor = TransactionBatchProcessor::<MockForkGraph>::new(
-        bank.execution_slot(),
-        0, // always set epoch to 0
+    let clock = sysvars.clock.as_ref();
+    let tx_processor = TransactionBatchProcessor::<FG>::new(
+        clock.map_or(bank.execution_slot(), |clock| clock.slot),
+        clock.map_or(0, |clock| clock.epoch), // epoch 0 unless set by the clock
         HashSet::new(),
     );
     create_executable_environment(
@@ -347,9 +2194,96 @@ where
 
     bank.set_clock()
         .map_err(|e| Error::BuilderError(e.to_string()))?;
-    tx_processor.fill_missing_sysvar_cache_entries(bank);
+    tx_processor.fill_missing_sysvar_cache_entries(&SysvarFilter {
+        inner: bank,
+        skipped: skipped_sysvars,
+        overrides: sysvars.accounts(),
+    });
 
-    register_builtins(bank, &tx_processor);
+    register_builtins_ex(bank, &tx_processor, builtins);
 
     Ok(tx_processor)
 }
+
+/// Sysvars replacing the ones provided by the bank, see
+/// `create_transaction_processor_with_sysvars`.
+#[derive(Debug, Clone, Default)]
+pub struct SysvarOverrides {
+    pub clock: Option<Clock>,
+    pub rent: Option<Rent>,
+}
+
+impl SysvarOverrides {
+    fn accounts(&self) -> Vec<(Pubkey, AccountSharedData)> {
+        let clock = self
+            .clock
+            .as_ref()
+            .map(|clock| (Clock::id(), sysvar_account(clock)));
+        let rent = self
+            .rent
+            .as_ref()
+            .map(|rent| (Rent::id(), sysvar_account(rent)));
+        clock.into_iter().chain(rent).collect()
+    }
+}
+
+fn sysvar_account<S: solana_sdk::sysvar::Sysvar>(sysvar: &S) -> AccountSharedData {
+    let mut account = AccountSharedData::default();
+    account.set_data(bincode::serialize(sysvar).expect("sysvars are serializable"));
+    account
+}
+
+/// Hides the skipped sysvar accounts and serves the overridden ones while filling the
+/// sysvar cache.
+struct SysvarFilter<'a, B> {
+    inner: &'a B,
+    skipped: &'a [Pubkey],
+    overrides: Vec<(Pubkey, AccountSharedData)>,
+}
+
+impl<B: TransactionProcessingCallback> TransactionProcessingCallback for SysvarFilter<'_, B> {
+    fn account_matches_owners(&self, account: &Pubkey, owners: &[Pubkey]) -> Option<usize> {
+        self.inner.account_matches_owners(account, owners)
+    }
+
+    fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        if self.skipped.contains(pubkey) {
+            return None;
+        }
+        if let Some((_, account)) = self.overrides.iter().find(|(key, _)| key == pubkey) {
+            return Some(account.clone());
+        }
+        self.inner.get_account_shared_data(pubkey)
+    }
+
+    fn add_builtin_account(&self, name: &str, program_id: &Pubkey) {
+        self.inner.add_builtin_account(name, program_id)
+    }
+}
+
+/// Serves the accounts written by earlier transactions of a batch on top of the bank.
+struct BatchOverlay<'a, B> {
+    inner: &'a B,
+    accounts: &'a HashMap<Pubkey, AccountSharedData>,
+}
+
+impl<B: TransactionProcessingCallback> TransactionProcessingCallback for BatchOverlay<'_, B> {
+    fn account_matches_owners(&self, account: &Pubkey, owners: &[Pubkey]) -> Option<usize> {
+        match self.accounts.get(account) {
+            Some(data) if data.lamports() == 0 => None,
+            Some(data) => owners.iter().position(|owner| data.owner() == owner),
+            None => self.inner.account_matches_owners(account, owners),
+        }
+    }
+
+    fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        match self.accounts.get(pubkey) {
+            Some(account) => Some(account.clone()),
+            None => self.inner.get_account_shared_data(pubkey),
+        }
+    }
+
+    fn add_builtin_account(&self, name: &str, program_id: &Pubkey) {
+        self.inner.add_builtin_account(name, program_id)
+    }
+}
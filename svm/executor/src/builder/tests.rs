@@ -1,16 +1,57 @@
-use std::env;
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    env,
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
 
-use igloo_interface::l2::executor::Init;
+use igloo_interface::l2::{
+    bank::{BankInfo, BankOperations},
+    executor::Init,
+};
+use solana_entry::entry::Entry;
+use solana_ledger::{
+    blockstore::{entries_to_test_shreds, Blockstore},
+    genesis_utils::create_genesis_config_with_leader,
+};
+use solana_program_runtime::loaded_programs::{BlockRelation, ForkGraph};
 use solana_sdk::{
-    account::ReadableAccount, clock::Clock, pubkey::Pubkey, sysvar::SysvarId,
-    transaction::TransactionError,
+    account::{AccountSharedData, ReadableAccount, WritableAccount},
+    bpf_loader_upgradeable,
+    clock::Clock,
+    fee::FeeStructure,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    nonce,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction::{self, SystemInstruction},
+    system_program,
+    sysvar::SysvarId,
+    transaction::{Transaction, TransactionError, VersionedTransaction},
+    vote,
 };
 use solana_svm::{
+    account_loader::CheckedTransactionDetails,
     transaction_processing_callback::TransactionProcessingCallback,
     transaction_results::TransactionExecutionResult,
 };
 
-use crate::{mock::bank::MockBankCallback, prelude::SimpleBuilder};
+use crate::{
+    builder::{
+        dsl::{format_account_metas, parse_account_metas},
+        output::TRUNCATED_MARKER,
+        parallel::{default_build_parallelism, TxSpec},
+        simple::Settings,
+    },
+    builtin::{Builtin, BuiltinSet},
+    env::DEPLOYMENT_SLOT,
+    mock::bank::{MockBankCallback, MockConfig},
+    prelude::{Error, SimpleBuilder},
+};
 
 fn get_program_path(name: &str) -> String {
     let mut dir = env::current_dir().unwrap();
@@ -169,3 +210,2175 @@ fn simple_transfer_failed_with_custom_check_error() {
         TransactionExecutionResult::NotExecuted(TransactionError::BlockhashNotFound)
     ));
 }
+
+#[test]
+fn strict_readonly_passes_when_readonly_accounts_untouched() {
+    let path = get_program_path("simple-transfer");
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .strict_readonly(true)
+        .build()
+        .expect("Read-only accounts should not be modified");
+
+    assert_eq!(result.execution_results.len(), 1);
+    assert!(result.execution_results[0]
+        .details()
+        .unwrap()
+        .status
+        .is_ok());
+}
+
+#[test]
+fn upgrade_program_works() {
+    let authority = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .upgrade_authority(Some(authority))
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    assert!(details.return_data.is_none());
+    let program_id = builder.get_program_id().unwrap();
+    let deployed = builder.program_data_after().unwrap();
+    assert_eq!(deployed.program_id, program_id);
+    assert_eq!(deployed.upgrade_authority, Some(authority));
+    let program = builder.account_after(&program_id).unwrap();
+    assert!(program.executable());
+    assert_eq!(program.owner(), &bpf_loader_upgradeable::id());
+
+    let new_buffer = std::fs::read(get_program_path("clock-sysvar")).unwrap();
+    let result = builder
+        .upgrade_program(new_buffer.clone(), authority)
+        .expect("Failed to upgrade program");
+    assert!(result.execution_results[0]
+        .details()
+        .unwrap()
+        .status
+        .is_ok());
+
+    let result = builder.build().expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    assert!(details.return_data.is_some());
+    assert!(!details
+        .log_messages
+        .as_ref()
+        .unwrap()
+        .contains(&"Program log: Hello, Solana!".to_string()));
+    assert_eq!(builder.get_program_id(), Some(program_id));
+
+    // without an authority the program is deployed immutable
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .upgradeable(None)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    let deployed = builder.program_data_after().unwrap();
+    assert_eq!(deployed.upgrade_authority, None);
+    let result = builder
+        .upgrade_program(new_buffer, authority)
+        .expect("Failed to execute upgrade");
+    assert!(!result.execution_results[0].was_executed_successfully());
+}
+
+#[test]
+fn account_dsl_roundtrip_works() {
+    let metas = vec![
+        AccountMeta::new(Pubkey::new_unique(), true),
+        AccountMeta::new(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(Pubkey::new_unique(), true),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+    ];
+    let dsl = format_account_metas(&metas);
+    assert_eq!(parse_account_metas(&dsl).unwrap(), metas);
+
+    let key = Pubkey::new_unique();
+    let parsed = parse_account_metas(&format!("Wr:{key}, S:{key}, r:{key}")).unwrap();
+    assert_eq!(
+        parsed,
+        vec![
+            AccountMeta::new(key, false),
+            AccountMeta::new_readonly(key, true),
+            AccountMeta::new_readonly(key, false),
+        ]
+    );
+}
+
+#[test]
+fn account_dsl_reports_malformed_token() {
+    let key = Pubkey::new_unique();
+    for token in [
+        format!("X:{key}"),
+        key.to_string(),
+        "W:not-a-pubkey".to_string(),
+    ] {
+        let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+        match builder.accounts_from_dsl(&format!("r:{key}, {token}")) {
+            Err(Error::BuilderError(msg)) => assert!(msg.contains(&token)),
+            _ => panic!("malformed token `{token}` should be rejected"),
+        }
+    }
+}
+
+#[test]
+fn on_transaction_callback_works() {
+    let path = get_program_path("hello-solana");
+    let observed = Rc::new(RefCell::new(vec![]));
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let inner = observed.clone();
+    let result = builder
+        .program_path(Some(path))
+        .on_transaction(Box::new(move |index, result| {
+            inner.borrow_mut().push((index, result.was_executed()));
+        }))
+        .build()
+        .expect("Failed to build transaction");
+
+    assert_eq!(result.execution_results.len(), 1);
+    assert_eq!(*observed.borrow(), vec![(0, true)]);
+}
+
+#[test]
+fn clock_sysvar_fails_when_skipped() {
+    let path = get_program_path("clock-sysvar");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .skip_sysvar(Clock::id())
+        .build()
+        .expect("Failed to build transaction");
+
+    assert_eq!(result.execution_results.len(), 1);
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_err());
+    assert!(details.return_data.is_none());
+}
+
+#[test]
+fn fee_payer_derived_from_seed() {
+    let path = get_program_path("hello-solana");
+    let base = Pubkey::new_unique();
+
+    let fee_payers = (0..2)
+        .map(|_| {
+            let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+            builder
+                .program_path(Some(path.clone()))
+                .fee_payer_seed(Some(base));
+            (0..2)
+                .map(|_| {
+                    let (_, tx) = builder.build_ex().expect("Failed to build transaction");
+                    tx.message.static_account_keys()[0]
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // the same seed yields the same fee payers in every run, distinct within a run
+    assert_eq!(fee_payers[0], fee_payers[1]);
+    assert_ne!(fee_payers[0][0], fee_payers[0][1]);
+}
+
+#[test]
+fn account_count_delta_works() {
+    let path = get_program_path("simple-transfer");
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    // the recipient does not exist yet and gets created by the transfer
+    let result = builder
+        .program_path(Some(path))
+        .account_with_balance(sender, Some(2_000_000), true, true)
+        .account_with_balance(recipient, None, false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(1_000_000u64.to_be_bytes().to_vec())
+        .v0_message(true)
+        .build()
+        .expect("Failed to build transaction");
+
+    assert!(result.execution_results[0]
+        .details()
+        .unwrap()
+        .status
+        .is_ok());
+    result.assert_account_count_delta(1);
+}
+
+#[test]
+fn account_count_includes_readonly_accounts() {
+    let readonly = Pubkey::new_unique();
+    let pre_account_count = |with_readonly: bool| {
+        let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+        builder.program_path(Some(get_program_path("hello-solana")));
+        if with_readonly {
+            builder.account_with_balance(readonly, Some(900000), false, false);
+        }
+        let result = builder.build().expect("Failed to build transaction");
+        result.assert_account_count_delta(0);
+        result.pre_account_count()
+    };
+    assert_eq!(pre_account_count(true), pre_account_count(false) + 1);
+}
+
+#[test]
+#[should_panic(expected = "account count changed by 0")]
+fn account_count_delta_mismatch_panics() {
+    let path = get_program_path("simple-transfer");
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .v0_message(true)
+        .build()
+        .expect("Failed to build transaction");
+
+    result.assert_account_count_delta(1);
+}
+
+#[test]
+fn raw_instructions_work() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .account_with_balance(sender, Some(2_000_000), true, true)
+        .account_with_balance(recipient, Some(1_000_000), false, true)
+        .raw_instructions(vec![
+            system_instruction::transfer(&sender, &recipient, 10),
+            system_instruction::transfer(&sender, &recipient, 20),
+        ])
+        .v0_message(true)
+        .build()
+        .expect("Failed to build transaction");
+
+    assert!(result.execution_results[0]
+        .details()
+        .unwrap()
+        .status
+        .is_ok());
+    let recipient_data = result.loaded_transactions[0]
+        .as_ref()
+        .unwrap()
+        .accounts
+        .iter()
+        .find(|key| key.0 == recipient)
+        .unwrap();
+    assert_eq!(recipient_data.1.lamports(), 1_000_030);
+}
+
+#[test]
+fn raw_instructions_reject_unknown_program() {
+    let program_id = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .raw_instructions(vec![Instruction::new_with_bytes(program_id, &[], vec![])])
+        .build();
+
+    match result {
+        Err(Error::BuilderError(msg)) => assert_eq!(
+            msg,
+            format!("Program {program_id} is neither deployed nor a builtin")
+        ),
+        _ => panic!("unexpected result"),
+    }
+}
+
+#[test]
+fn underfunded_fee_payer_reports_diagnostic() {
+    let path = get_program_path("hello-solana");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .settings(Settings {
+            fee_payer_balance: 100,
+        })
+        .build()
+        .expect("Failed to build transaction");
+
+    let required_fee = FeeStructure::default().lamports_per_signature;
+    let diagnostic = result
+        .fee_payer_diagnostic()
+        .expect("Fee payer diagnostic missing");
+    assert_eq!(diagnostic.fee_payer, result.fee_payer().fee_payer);
+    assert_eq!(diagnostic.balance, 100);
+    assert_eq!(diagnostic.required_fee, required_fee);
+    assert_eq!(diagnostic.error, TransactionError::InsufficientFundsForFee);
+
+    let message = diagnostic.to_string();
+    assert!(message.contains(&diagnostic.fee_payer.to_string()));
+    assert!(message.contains("balance 100 lamports"));
+    assert!(message.contains(&format!("required {required_fee} lamports")));
+}
+
+#[test]
+fn debug_report_works() {
+    let path = get_program_path("simple-transfer");
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .v0_message(true)
+        .build()
+        .expect("Failed to build transaction");
+
+    let report = result.debug_report();
+    assert!(report.contains("Transaction 0: executed"), "{report}");
+    assert!(report.contains("Status: Ok(())"), "{report}");
+    assert!(
+        report.contains(&format!("{recipient}: lamports 900010")),
+        "{report}"
+    );
+    assert!(
+        report.contains(&format!("{sender}: lamports 899990")),
+        "{report}"
+    );
+    // the program account is not modified
+    assert!(!report.contains("executable true"), "{report}");
+}
+
+#[test]
+fn active_features_works() {
+    let builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    assert!(builder.active_features().is_empty());
+}
+
+#[test]
+fn feature_toggles_work() {
+    let feature = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    assert!(builder.active_features().is_empty());
+
+    builder.activate_feature(feature).activate_feature(other);
+    assert_eq!(builder.active_features(), HashSet::from([feature, other]));
+
+    builder.deactivate_feature(other);
+    assert_eq!(builder.active_features(), HashSet::from([feature]));
+}
+
+#[test]
+fn toggling_features_between_builds_works() {
+    let feature = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .upgradeable(None);
+    for active in [false, true, false] {
+        if active {
+            builder.activate_feature(feature);
+        } else {
+            builder.deactivate_feature(feature);
+        }
+        // the processor created by the first build is kept
+        let result = builder.build().expect("Failed to build transaction");
+        assert!(result.execution_results[0].was_executed_successfully());
+        assert_eq!(builder.active_features().contains(&feature), active);
+    }
+}
+
+#[test]
+fn max_loaded_accounts_data_size_works() {
+    let path = get_program_path("hello-solana");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path.clone()))
+        .max_loaded_accounts_data_size(64 * 1024 * 1024)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0]
+        .details()
+        .unwrap()
+        .status
+        .is_ok());
+
+    // the program alone is larger than the limit
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .max_loaded_accounts_data_size(1024)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(matches!(
+        result.execution_results[0],
+        TransactionExecutionResult::NotExecuted(
+            TransactionError::MaxLoadedAccountsDataSizeExceeded
+        )
+    ));
+}
+
+#[test]
+fn run_feature_matrix_works() {
+    let path = get_program_path("hello-solana");
+    let feature = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder.program_path(Some(path.clone()));
+    let outputs = builder
+        .run_feature_matrix(&Default::default(), vec![feature])
+        .expect("Failed to run feature matrix");
+
+    assert_eq!(
+        outputs
+            .iter()
+            .map(|(active, _)| *active)
+            .collect::<Vec<_>>(),
+        vec![false, true]
+    );
+    for (_, output) in outputs.iter() {
+        assert!(output.execution_results[0]
+            .details()
+            .unwrap()
+            .status
+            .is_ok());
+    }
+    assert!(builder.active_features().is_empty());
+
+    // the last run starts from a fresh bank, so it holds the same accounts as a single build
+    let mut single = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    single
+        .program_path(Some(path))
+        .build()
+        .expect("Failed to build transaction");
+    assert_eq!(
+        builder.get_bank().account_shared_data.borrow().len(),
+        single.get_bank().account_shared_data.borrow().len()
+    );
+}
+
+#[test]
+fn deploy_at_fixed_program_id_works() {
+    let path = get_program_path("hello-solana");
+    let program_id = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder.program_path(Some(path)).program_id(program_id);
+    for _ in 0..2 {
+        let result = builder.build().expect("Failed to build transaction");
+        assert!(result.execution_results[0]
+            .details()
+            .unwrap()
+            .status
+            .is_ok());
+        assert_eq!(builder.get_program_id(), Some(program_id));
+    }
+}
+
+#[test]
+fn deploy_at_occupied_program_id_fails() {
+    let path = get_program_path("hello-solana");
+    let program_id = Pubkey::new_unique();
+
+    let mut bank = MockBankCallback::default();
+    let mut account = AccountSharedData::default();
+    account.set_lamports(1000);
+    bank.insert_account(program_id, account).unwrap();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .bank(bank)
+        .program_path(Some(path))
+        .program_id(program_id)
+        .build();
+
+    match result {
+        Err(Error::BuilderError(msg)) => assert_eq!(
+            msg,
+            format!("Program id {program_id} is already occupied by another account")
+        ),
+        _ => panic!("unexpected result"),
+    }
+}
+
+#[test]
+fn deploy_replaces_program_at_program_id() {
+    let program_id = Pubkey::new_unique();
+
+    let mut bank = MockBankCallback::default();
+    let mut account = AccountSharedData::new(1000, 0, &bpf_loader_upgradeable::id());
+    account.set_executable(true);
+    bank.insert_account(program_id, account).unwrap();
+
+    // the clock program sets return data, unlike the program it replaces
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .bank(bank)
+        .program_path(Some(get_program_path("clock-sysvar")))
+        .program_id(program_id)
+        .build()
+        .expect("Failed to build transaction");
+
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    assert!(details.return_data.is_some());
+    assert_eq!(builder.get_program_id(), Some(program_id));
+}
+
+#[test]
+fn replay_slot_works() {
+    let ledger_path = tempfile::tempdir().unwrap();
+    let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let transfer = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &recipient,
+            1_000_000,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let vote = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(
+            vote::program::id(),
+            &[],
+            vec![],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let entries = vec![Entry::new(&Hash::default(), 1, vec![vote, transfer])];
+    let shreds = entries_to_test_shreds(&entries, 1, 0, true, 0, true);
+    blockstore.insert_shreds(shreds, None, false).unwrap();
+
+    let mut bank = MockBankCallback::default();
+    let mut account = AccountSharedData::default();
+    account.set_lamports(2_000_000);
+    bank.insert_account(payer.pubkey(), account).unwrap();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let outputs = builder
+        .bank(bank)
+        .replay_slot(&blockstore, 1)
+        .expect("Failed to replay slot");
+
+    // the vote transaction is skipped
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs[0].execution_results[0]
+        .details()
+        .unwrap()
+        .status
+        .is_ok());
+    let recipient_data = outputs[0].loaded_transactions[0]
+        .as_ref()
+        .unwrap()
+        .accounts
+        .iter()
+        .find(|key| key.0 == recipient)
+        .unwrap();
+    assert_eq!(recipient_data.1.lamports(), 1_000_000);
+}
+
+#[test]
+fn build_parallel_works() {
+    let path = get_program_path("simple-transfer");
+    let system_account = Pubkey::from([0u8; 32]);
+    let specs = (0..8u64)
+        .map(|amount| {
+            let mut sender = AccountSharedData::default();
+            sender.set_lamports(900000);
+            let mut recipient = AccountSharedData::default();
+            recipient.set_lamports(900000);
+            TxSpec {
+                program_path: Some(path.clone()),
+                accounts: vec![
+                    (AccountMeta::new(Pubkey::new_unique(), true), Some(sender)),
+                    (
+                        AccountMeta::new(Pubkey::new_unique(), false),
+                        Some(recipient),
+                    ),
+                    (AccountMeta::new_readonly(system_account, false), None),
+                ],
+                calldata: amount.to_be_bytes().to_vec(),
+                v0_message: true,
+                ..Default::default()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    assert!(default_build_parallelism() >= 1);
+    for results in [
+        SimpleBuilder::<MockBankCallback>::build_parallel(&Default::default(), specs.clone()),
+        SimpleBuilder::<MockBankCallback>::build_parallel_ex(&Default::default(), specs.clone(), 4),
+    ] {
+        assert_eq!(results.len(), specs.len());
+        for (amount, (result, spec)) in results.into_iter().zip(specs.iter()).enumerate() {
+            let result = result.expect("Failed to build transaction");
+            let recipient = spec.accounts[1].0.pubkey;
+            let recipient_data = result.loaded_transactions[0]
+                .as_ref()
+                .unwrap()
+                .accounts
+                .iter()
+                .find(|key| key.0 == recipient)
+                .unwrap();
+            assert_eq!(recipient_data.1.lamports(), 900000 + amount as u64);
+        }
+    }
+}
+
+#[test]
+fn per_instruction_units_works() {
+    let path = get_program_path("hello-solana");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path.clone()))
+        .build()
+        .expect("Failed to build transaction");
+    let executed_units = result.execution_results[0]
+        .details()
+        .unwrap()
+        .executed_units;
+    assert_eq!(result.per_instruction_units(), vec![executed_units]);
+
+    // the compute budget builtin does not report its consumption
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .max_loaded_accounts_data_size(64 * 1024 * 1024)
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert_eq!(result.per_instruction_units(), vec![details.executed_units]);
+    assert!(details
+        .log_messages
+        .as_ref()
+        .unwrap()
+        .iter()
+        .any(|log| log.contains(" consumed ")));
+}
+
+#[cfg(feature = "token")]
+#[test]
+fn token_account_works() {
+    let path = get_program_path("hello-solana");
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let token_account = builder.token_account(mint, owner, 42);
+    let result = builder
+        .program_path(Some(path))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert_eq!(result.token_balance_after(&token_account), 42);
+}
+
+#[test]
+fn build_batch_retries_on_missing_account() {
+    let payer = Keypair::new();
+    let intermediate = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    // spends from an account only created by the second transaction
+    let spend = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &intermediate.pubkey(),
+            &recipient,
+            1_000_000,
+        )],
+        Some(&intermediate.pubkey()),
+        &[&intermediate],
+        Hash::default(),
+    );
+    let fund = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &intermediate.pubkey(),
+            2_000_000,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let batch = vec![spend.into(), fund.into()];
+
+    for (max_passes, spent) in [(1, false), (3, true)] {
+        let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+        let outputs = builder
+            .account_with_balance(payer.pubkey(), Some(3_000_000), true, true)
+            .retry_on_missing_account(max_passes)
+            .build_batch(batch.clone())
+            .expect("Failed to build batch");
+
+        assert_eq!(outputs.len(), 2);
+        assert!(outputs[1].execution_results[0].was_executed_successfully());
+        assert_eq!(
+            outputs[0].execution_results[0].was_executed_successfully(),
+            spent
+        );
+        assert_eq!(
+            builder
+                .get_bank()
+                .get_account_shared_data(&recipient)
+                .map_or(0, |account| account.lamports()),
+            if spent { 1_000_000 } else { 0 }
+        );
+    }
+}
+
+#[test]
+fn build_batch_notifies_batch_indices() {
+    let payer = Keypair::new();
+    let intermediate = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let transfer = |from: &Keypair, to: &Pubkey, lamports: u64| -> VersionedTransaction {
+        Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(&from.pubkey(), to, lamports)],
+            Some(&from.pubkey()),
+            &[from],
+            Hash::default(),
+        )
+        .into()
+    };
+    // the first transaction is retried once the second one created its account
+    let batch = vec![
+        transfer(&intermediate, &recipient, 1_000_000),
+        transfer(&payer, &intermediate.pubkey(), 2_000_000),
+        transfer(&payer, &recipient, 1_000_000),
+    ];
+    let observed = Rc::new(RefCell::new(vec![]));
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let inner = observed.clone();
+    let outputs = builder
+        .account_with_balance(payer.pubkey(), Some(4_000_000), true, true)
+        .retry_on_missing_account(3)
+        .on_transaction(Box::new(move |index, result| {
+            inner
+                .borrow_mut()
+                .push((index, result.was_executed_successfully()));
+        }))
+        .build_batch(batch)
+        .expect("Failed to build batch");
+
+    assert_eq!(outputs.len(), 3);
+    assert_eq!(*observed.borrow(), vec![(0, true), (1, true), (2, true)]);
+}
+
+#[test]
+fn last_build_cache_stats_works() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .upgrade_authority(Some(Pubkey::new_unique()));
+
+    let result = builder.build().expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    // the freshly deployed program has to be loaded
+    assert!(builder.last_build_cache_stats().misses > 0);
+
+    let result = builder.build().expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    let stats = builder.last_build_cache_stats();
+    assert!(stats.hits > 0);
+    assert_eq!(stats.misses, 0);
+    assert_eq!(stats.recompiles, 0);
+}
+
+#[test]
+fn max_log_bytes_truncates_logs() {
+    let path = get_program_path("hello-solana");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path.clone()))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(!result.logs_truncated());
+    let logs = result.execution_results[0]
+        .details()
+        .unwrap()
+        .log_messages
+        .clone()
+        .unwrap();
+
+    let max_log_bytes = logs[0].len() + 4;
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .max_log_bytes(max_log_bytes)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.logs_truncated());
+    let truncated = result.execution_results[0]
+        .details()
+        .unwrap()
+        .log_messages
+        .clone()
+        .unwrap();
+    assert_eq!(
+        truncated,
+        vec![
+            logs[0].clone(),
+            format!("{}{}", &logs[1][..4], TRUNCATED_MARKER)
+        ]
+    );
+}
+
+#[test]
+fn max_return_data_bytes_truncates_return_data() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("clock-sysvar")))
+        .max_return_data_bytes(4)
+        .build()
+        .expect("Failed to build transaction");
+
+    assert!(result.return_data_truncated());
+    let return_data = result.execution_results[0]
+        .details()
+        .unwrap()
+        .return_data
+        .as_ref()
+        .unwrap();
+    assert_eq!(return_data.data.len(), 4);
+    assert!(result.debug_report().contains(TRUNCATED_MARKER));
+}
+
+#[test]
+fn assert_lamport_neutral_works() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .build()
+        .expect("Failed to build transaction");
+
+    let fee_payer = result.fee_payer().fee_payer;
+    let required_fee = result.fee_payer().required_fee;
+    assert!(required_fee > 0);
+    assert_eq!(
+        result.lamport_deltas(),
+        vec![(fee_payer, -(required_fee as i64))]
+    );
+    result.assert_lamport_neutral(&fee_payer);
+}
+
+#[test]
+#[should_panic(expected = "lamports changed by")]
+fn assert_lamport_neutral_catches_transfers() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .build()
+        .expect("Failed to build transaction");
+
+    result.assert_lamport_neutral(&result.fee_payer().fee_payer);
+}
+
+#[test]
+fn required_signers_works() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    for v0_message in [false, true] {
+        let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+        let result = builder
+            .program_path(Some(get_program_path("simple-transfer")))
+            .account_with_balance(sender, Some(900000), true, true)
+            .account_with_balance(recipient, Some(900000), false, true)
+            .account_with_balance(system_account, None, false, false)
+            .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+            .v0_message(v0_message)
+            .build()
+            .expect("Failed to build transaction");
+
+        assert_eq!(
+            builder.required_signers(),
+            vec![result.fee_payer().fee_payer, sender]
+        );
+    }
+}
+
+#[test]
+fn expired_blockhash_fails() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let (result, tx) = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .expired_blockhash(true)
+        .build_ex()
+        .expect("Failed to build transaction");
+
+    assert_ne!(
+        *tx.message.recent_blockhash(),
+        builder.get_bank().last_blockhash()
+    );
+    assert!(matches!(
+        result.execution_results[0],
+        TransactionExecutionResult::NotExecuted(TransactionError::BlockhashNotFound)
+    ));
+}
+
+#[test]
+fn result_iterators_work() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .build()
+        .expect("Failed to build transaction");
+
+    let summaries = result.results().collect::<Vec<_>>();
+    assert_eq!(summaries.len(), 1);
+    assert!(summaries[0].executed);
+    assert_eq!(summaries[0].status, Ok(()));
+    assert!(summaries[0].executed_units > 0);
+    assert!(summaries[0]
+        .logs
+        .contains(&"Program log: Hello, Solana!".to_string()));
+    assert_eq!(result.successful().count(), 1);
+    assert_eq!(result.failed().count(), 0);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .check_result(Err(TransactionError::BlockhashNotFound))
+        .build()
+        .expect("Failed to build transaction");
+
+    let failed = result.failed().collect::<Vec<_>>();
+    assert_eq!(failed.len(), 1);
+    assert!(!failed[0].executed);
+    assert_eq!(failed[0].status, Err(TransactionError::BlockhashNotFound));
+    assert_eq!(result.successful().count(), 0);
+}
+
+#[test]
+fn initial_compute_units_works() {
+    let path = get_program_path("hello-solana");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path.clone()))
+        .build()
+        .expect("Failed to build transaction");
+    let required_units = result.execution_results[0]
+        .details()
+        .unwrap()
+        .executed_units;
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path.clone()))
+        .initial_compute_units(required_units + 10_000)
+        .max_loaded_accounts_data_size(64 * 1024 * 1024)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+
+    // the compute budget instruction consumes units as well, so the program runs out
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .initial_compute_units(required_units)
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_err());
+    assert!(details
+        .log_messages
+        .as_ref()
+        .unwrap()
+        .iter()
+        .any(|log| log.contains("exceeded CUs meter")));
+}
+
+#[test]
+fn syscall_counts_work() {
+    let path = get_program_path("hello-solana");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(path))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(builder.last_build_syscall_counts().is_empty());
+
+    let result = builder
+        .count_syscalls(true)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    let counts = builder.last_build_syscall_counts();
+    assert_eq!(counts.get("sol_log_"), Some(&1));
+    assert!(!counts.contains_key("sol_invoke_signed_rust"));
+}
+
+#[test]
+fn canonical_account_order_works() {
+    let mut writable = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut readonly = [Pubkey::new_unique(), Pubkey::new_unique()];
+    // insert in descending order
+    writable.sort_by(|a, b| b.cmp(a));
+    readonly.sort_by(|a, b| b.cmp(a));
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .canonical_account_order(true);
+    for key in writable {
+        builder.account(AccountMeta::new(key, false), None);
+    }
+    for key in readonly {
+        builder.account(AccountMeta::new_readonly(key, false), None);
+    }
+    let (result, tx) = builder.build_ex().expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+
+    // fee payer, writable accounts, readonly accounts and the program id
+    let keys = tx.message.static_account_keys();
+    assert_eq!(keys.len(), 6);
+    assert!(keys[1] < keys[2]);
+    assert!(keys[3] < keys[4]);
+    assert_eq!(
+        keys[1..5].iter().copied().collect::<HashSet<_>>(),
+        writable.into_iter().chain(readonly).collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn from_genesis_works() {
+    let genesis = create_genesis_config_with_leader(1_000_000_000, &Pubkey::new_unique(), 10_000);
+    let mint = genesis.mint_keypair.pubkey();
+
+    let mut builder =
+        SimpleBuilder::<MockBankCallback>::from_genesis(&Default::default(), &genesis).unwrap();
+    assert!(builder
+        .get_bank()
+        .get_account_shared_data(&genesis.validator_pubkey)
+        .is_some());
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+
+    let fee_payer_balance = Settings::default().fee_payer_balance;
+    assert_eq!(result.fee_payer().balance, fee_payer_balance);
+    assert_eq!(
+        builder
+            .get_bank()
+            .get_account_shared_data(&mint)
+            .unwrap()
+            .lamports(),
+        1_000_000_000 - fee_payer_balance
+    );
+}
+
+#[test]
+fn return_data_cap_works() {
+    // the program returns the 8 bytes of the clock timestamp
+    let path = get_program_path("clock-sysvar");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path.clone()))
+        .return_data_cap(8)
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    assert_eq!(details.return_data.as_ref().unwrap().data.len(), 8);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .return_data_cap(7)
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_err());
+    assert!(details
+        .log_messages
+        .as_ref()
+        .unwrap()
+        .iter()
+        .any(|log| log.contains("Return data too large")));
+}
+
+#[test]
+fn record_intermediate_state_works() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .account_with_balance(sender, Some(2_000_000), true, true)
+        .account_with_balance(recipient, Some(1_000_000), false, true)
+        .raw_instructions(vec![
+            system_instruction::transfer(&sender, &recipient, 10),
+            system_instruction::transfer(&sender, &recipient, 20),
+            system_instruction::transfer(&sender, &recipient, 5_000_000),
+        ])
+        .initial_compute_units(10_000)
+        .record_intermediate_state(true)
+        .build()
+        .expect("Failed to build transaction");
+
+    assert!(result.execution_results[0]
+        .details()
+        .unwrap()
+        .status
+        .is_err());
+    // the third transfer fails, so only the states after the first two are recorded, the
+    // compute budget instruction has no state of its own
+    let states = result.intermediate_states();
+    assert_eq!(states.len(), 2);
+    assert_eq!(states[0][&recipient].lamports(), 1_000_010);
+    assert_eq!(states[0][&sender].lamports(), 1_999_990);
+    assert_eq!(states[1][&recipient].lamports(), 1_000_030);
+    assert_eq!(states[1][&sender].lamports(), 1_999_970);
+
+    let result = builder
+        .record_intermediate_state(false)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.intermediate_states().is_empty());
+}
+
+#[test]
+fn record_instruction_units_works() {
+    let path = get_program_path("hello-solana");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .max_loaded_accounts_data_size(64 * 1024 * 1024)
+        .build()
+        .expect("Failed to build transaction");
+    let executed_units = result.execution_results[0]
+        .details()
+        .unwrap()
+        .executed_units;
+    assert_eq!(result.per_instruction_units(), vec![executed_units]);
+
+    let result = builder
+        .record_instruction_units(true)
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    // the compute budget instruction is followed by the program
+    let units = result.per_instruction_units();
+    assert_eq!(units.len(), 2);
+    assert!(units.iter().all(|units| *units > 0));
+    assert_eq!(units.iter().sum::<u64>(), details.executed_units);
+
+    // the third transfer fails, nothing runs after it
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .account_with_balance(sender, Some(2_000_000), true, true)
+        .account_with_balance(recipient, Some(1_000_000), false, true)
+        .raw_instructions(vec![
+            system_instruction::transfer(&sender, &recipient, 10),
+            system_instruction::transfer(&sender, &recipient, 5_000_000),
+            system_instruction::transfer(&sender, &recipient, 20),
+        ])
+        .record_instruction_units(true)
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_err());
+    let units = result.per_instruction_units();
+    assert_eq!(units.len(), 3);
+    assert!(units[0] > 0);
+    assert_eq!(units[0], units[1]);
+    assert_eq!(units[2], 0);
+    assert_eq!(units.iter().sum::<u64>(), details.executed_units);
+}
+
+#[test]
+fn transactions_work() {
+    let path = get_program_path("simple-transfer");
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+    let accounts = |lamports: Option<u64>| {
+        let account = lamports.map(|lamports| {
+            let mut account = AccountSharedData::default();
+            account.set_lamports(lamports);
+            account
+        });
+        vec![
+            (AccountMeta::new(sender, true), account.clone()),
+            (AccountMeta::new(recipient, false), account),
+            (AccountMeta::new_readonly(system_account, false), None),
+        ]
+    };
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .transactions(vec![
+            (600_000u64.to_be_bytes().to_vec(), accounts(Some(900_000))),
+            // only affordable with the balance left by the first transfer
+            (300_000u64.to_be_bytes().to_vec(), accounts(None)),
+            (1u64.to_be_bytes().to_vec(), accounts(None)),
+        ])
+        .build()
+        .expect("Failed to build transaction");
+
+    assert_eq!(result.execution_results.len(), 3);
+    assert_eq!(result.loaded_transactions.len(), 3);
+    let statuses = result
+        .results()
+        .map(|summary| summary.is_ok())
+        .collect::<Vec<_>>();
+    assert_eq!(statuses, vec![true, true, false]);
+    let lamports = |index: usize, key: &Pubkey| {
+        result.loaded_transactions[index]
+            .as_ref()
+            .unwrap()
+            .accounts
+            .iter()
+            .find(|(loaded, _)| loaded == key)
+            .unwrap()
+            .1
+            .lamports()
+    };
+    assert_eq!(lamports(0, &recipient), 1_500_000);
+    assert_eq!(lamports(1, &sender), 0);
+    assert_eq!(lamports(1, &recipient), 1_800_000);
+
+    assert!(matches!(builder.build_ex(), Err(Error::BuilderError(_))));
+}
+
+#[test]
+fn fee_is_the_same_for_legacy_and_v0_messages() {
+    let signer = Pubkey::new_unique();
+
+    let fees = [false, true].map(|v0_message| {
+        let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+        let result = builder
+            .program_path(Some(get_program_path("hello-solana")))
+            .account_with_balance(signer, Some(1_000_000), true, false)
+            .v0_message(v0_message)
+            .build()
+            .expect("Failed to build transaction");
+
+        let fee_payer = result.fee_payer().fee_payer;
+        let required_fee = result.fee_payer().required_fee;
+        assert_eq!(
+            result.lamport_deltas(),
+            vec![(fee_payer, -(required_fee as i64))]
+        );
+        required_fee
+    });
+
+    // the fee payer and the additional signer
+    assert_eq!(fees[0], 2 * FeeStructure::default().lamports_per_signature);
+    assert_eq!(fees[0], fees[1]);
+}
+
+#[test]
+fn compute_unit_limit_and_price_work() {
+    let path = get_program_path("hello-solana");
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path.clone()))
+        .compute_unit_limit(20_000)
+        .compute_unit_price(1_000_000)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert!(result.consumed_units() <= 20_000);
+
+    // one lamport per unit of the limit on top of the signature fee
+    let fee_payer = result.fee_payer();
+    assert_eq!(
+        fee_payer.required_fee,
+        FeeStructure::default().lamports_per_signature + 20_000
+    );
+    assert_eq!(
+        result.lamport_deltas(),
+        vec![(fee_payer.fee_payer, -(fee_payer.required_fee as i64))]
+    );
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(path))
+        .compute_unit_limit(500)
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_err());
+    assert_eq!(result.consumed_units(), 500);
+}
+
+#[test]
+fn verify_program_reports_diagnostic() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_buffer(Some(b"not an ELF file".to_vec()))
+        .build();
+
+    match result {
+        Err(Error::BuilderError(msg)) => {
+            let diagnostic = msg
+                .strip_prefix("Failed to load program ELF: ")
+                .expect("unexpected error");
+            assert!(!diagnostic.is_empty());
+        }
+        _ => panic!("unexpected result"),
+    }
+
+    // the invalid program only fails at execution without verification
+    let result = builder
+        .verify_program(false)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(!result.execution_results[0].was_executed_successfully());
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+}
+
+#[test]
+fn program_data_after_works() {
+    let authority = Pubkey::new_unique();
+    let path = get_program_path("hello-solana");
+    let program_len = std::fs::read(&path).unwrap().len();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(path.clone()))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(builder.program_data_after().is_none());
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(path))
+        .upgrade_authority(Some(authority))
+        .build()
+        .expect("Failed to build transaction");
+    let deployed = builder.program_data_after().unwrap();
+    assert_eq!(deployed.program_id, builder.get_program_id().unwrap());
+    assert_eq!(deployed.slot, DEPLOYMENT_SLOT);
+    assert_eq!(deployed.upgrade_authority, Some(authority));
+    assert_eq!(deployed.program_len, program_len * 2);
+
+    let new_buffer = std::fs::read(get_program_path("clock-sysvar")).unwrap();
+    builder
+        .upgrade_program(new_buffer, authority)
+        .expect("Failed to upgrade program");
+    let upgraded = builder.program_data_after().unwrap();
+    assert_eq!(upgraded.programdata_address, deployed.programdata_address);
+    assert!(upgraded.slot > deployed.slot);
+    assert_eq!(upgraded.upgrade_authority, Some(authority));
+    assert_eq!(upgraded.program_len, deployed.program_len);
+}
+
+#[test]
+fn last_execution_works() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    assert!(builder.last_execution().is_none());
+
+    let result = builder
+        .program_path(Some(get_program_path("clock-sysvar")))
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+
+    let execution = builder.last_execution().unwrap();
+    assert_eq!(execution.result, Ok(()));
+    assert_eq!(&execution.logs, details.log_messages.as_ref().unwrap());
+    assert_eq!(execution.return_data.len(), 8);
+    assert_eq!(execution.compute_units, details.executed_units);
+
+    let result = builder
+        .expired_blockhash(true)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(!result.execution_results[0].was_executed());
+    let execution = builder.last_execution().unwrap();
+    assert_eq!(execution.result, Err(TransactionError::BlockhashNotFound));
+    assert!(execution.logs.is_empty());
+    assert!(execution.return_data.is_empty());
+    assert_eq!(execution.compute_units, 0);
+}
+
+#[test]
+fn record_cpi_works() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10]);
+
+    let result = builder.build().expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    assert!(details.inner_instructions.is_none());
+
+    let (result, transaction) = builder
+        .record_cpi(true)
+        .build_ex()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    let inner_instructions = details.inner_instructions.as_ref().unwrap();
+    assert_eq!(inner_instructions.len(), 1);
+    assert_eq!(inner_instructions[0].len(), 1);
+
+    // the program transfers through the system program
+    let keys = transaction.message.static_account_keys();
+    let inner = &inner_instructions[0][0];
+    assert_eq!(inner.stack_height, 2);
+    assert_eq!(
+        keys[inner.instruction.program_id_index as usize],
+        system_account
+    );
+    let accounts = inner
+        .instruction
+        .accounts
+        .iter()
+        .map(|index| keys[*index as usize])
+        .collect::<Vec<_>>();
+    assert_eq!(accounts, vec![sender, recipient]);
+    assert_eq!(
+        bincode::deserialize::<SystemInstruction>(&inner.instruction.data).unwrap(),
+        SystemInstruction::Transfer { lamports: 10 }
+    );
+}
+
+#[test]
+fn message_hash_works() {
+    for v0_message in [false, true] {
+        let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+        builder
+            .program_path(Some(get_program_path("hello-solana")))
+            .v0_message(v0_message);
+
+        let (result, transaction) = builder.build_ex().expect("Failed to build transaction");
+        assert_eq!(result.message_hash(), transaction.message.hash());
+        assert_eq!(result.message_hashes(), &[transaction.message.hash()]);
+
+        let (transaction, hash) = builder
+            .build_unexecuted()
+            .expect("Failed to build transaction");
+        assert_eq!(hash, transaction.message.hash());
+        let results = builder
+            .build_batch(vec![transaction])
+            .expect("Failed to build transaction");
+        assert!(results[0].execution_results[0].was_executed_successfully());
+        assert_eq!(results[0].message_hash(), hash);
+    }
+}
+
+#[test]
+fn address_lookup_table_works() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+    let table = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let (result, transaction) = builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .address_lookup_table(table, vec![Pubkey::new_unique(), recipient, system_account])
+        .v0_message(true)
+        .build_ex()
+        .expect("Failed to build transaction");
+
+    assert!(result.execution_results[0].was_executed_successfully());
+    let lookups = transaction.message.address_table_lookups().unwrap();
+    assert_eq!(lookups.len(), 1);
+    assert_eq!(lookups[0].account_key, table);
+    assert_eq!(lookups[0].writable_indexes, vec![1]);
+    assert_eq!(lookups[0].readonly_indexes, vec![2]);
+    assert!(!transaction
+        .message
+        .static_account_keys()
+        .contains(&recipient));
+    assert_eq!(
+        result
+            .lamport_deltas()
+            .into_iter()
+            .find(|(key, _)| *key == recipient),
+        Some((recipient, 10))
+    );
+    assert!(builder.get_bank().get_account_shared_data(&table).is_some());
+}
+
+#[test]
+fn explicit_fee_payer_works() {
+    let fee_payer = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .fee_payer(fee_payer)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert_eq!(result.fee_payer().fee_payer, fee_payer);
+    assert_eq!(
+        result.fee_payer().balance,
+        Settings::default().fee_payer_balance
+    );
+
+    // an existing fee payer account is used as is
+    let mut account = AccountSharedData::default();
+    account.set_lamports(1_000_000);
+    builder
+        .bank_mut()
+        .insert_account(fee_payer, account)
+        .unwrap();
+    let result = builder.build().expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert_eq!(result.fee_payer().fee_payer, fee_payer);
+    assert_eq!(result.fee_payer().balance, 1_000_000);
+}
+
+#[test]
+fn expect_owner_reports_mismatches() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let missing = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+    let owner = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .expect_owner(sender, system_account)
+        .expect_owner(recipient, owner)
+        .expect_owner(missing, owner);
+
+    match builder.build() {
+        Err(Error::BuilderError(msg)) => assert_eq!(
+            msg,
+            format!(
+                "Unexpected account owners: {recipient} is owned by {system_account}, \
+                 expected {owner}; {missing} does not exist, expected owner {owner}"
+            )
+        ),
+        _ => panic!("unexpected result"),
+    }
+    assert!(builder.last_execution().is_none());
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .expect_owner(sender, system_account)
+        .expect_owner(recipient, system_account)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+}
+
+#[test]
+fn custom_clock_works() {
+    let unix_timestamp = 1_700_000_000;
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("clock-sysvar")))
+        .clock(10, 2, unix_timestamp)
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    let return_data = details.return_data.as_ref().unwrap();
+    let time = i64::from_be_bytes(return_data.data[0..8].try_into().unwrap());
+    assert_eq!(time, unix_timestamp);
+
+    let clock_data = builder
+        .get_bank()
+        .get_account_shared_data(&Clock::id())
+        .unwrap();
+    let clock_info: Clock = bincode::deserialize(clock_data.data()).unwrap();
+    assert_eq!(clock_info.slot, 10);
+    assert_eq!(clock_info.epoch, 2);
+    assert_eq!(clock_info.unix_timestamp, unix_timestamp);
+
+    // a new clock replaces the processor caching the previous one
+    let result = builder
+        .clock(11, 2, unix_timestamp + 1)
+        .build()
+        .expect("Failed to build transaction");
+    let return_data = result.execution_results[0]
+        .details()
+        .unwrap()
+        .return_data
+        .as_ref()
+        .unwrap();
+    let time = i64::from_be_bytes(return_data.data[0..8].try_into().unwrap());
+    assert_eq!(time, unix_timestamp + 1);
+
+    match builder.clock(DEPLOYMENT_SLOT, 0, unix_timestamp).build() {
+        Err(Error::BuilderError(msg)) => assert_eq!(
+            msg,
+            format!(
+                "Clock slot {DEPLOYMENT_SLOT} is not after the deployment slot {DEPLOYMENT_SLOT}"
+            )
+        ),
+        _ => panic!("unexpected result"),
+    }
+}
+
+#[test]
+fn rent_override_works() {
+    let payer = Pubkey::new_unique();
+    let space = 100;
+    let rent = Rent {
+        lamports_per_byte_year: Rent::default().lamports_per_byte_year * 10,
+        ..Rent::default()
+    };
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .account_with_balance(payer, Some(100_000_000), true, true)
+        .rent(rent.clone());
+
+    // exempt under the default rent, but not under the overridden one
+    let account = Pubkey::new_unique();
+    let result = builder
+        .raw_instructions(vec![system_instruction::create_account(
+            &payer,
+            &account,
+            Rent::default().minimum_balance(space),
+            space as u64,
+            &Pubkey::new_unique(),
+        )])
+        .build()
+        .expect("Failed to build transaction");
+    assert!(matches!(
+        result.execution_results[0].details().unwrap().status,
+        Err(TransactionError::InsufficientFundsForRent { .. })
+    ));
+
+    let account = Pubkey::new_unique();
+    let result = builder
+        .raw_instructions(vec![system_instruction::create_account(
+            &payer,
+            &account,
+            rent.minimum_balance(space),
+            space as u64,
+            &Pubkey::new_unique(),
+        )])
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+
+    let rent_data = builder
+        .get_bank()
+        .get_account_shared_data(&Rent::id())
+        .unwrap();
+    let rent_info: Rent = bincode::deserialize(rent_data.data()).unwrap();
+    assert_eq!(rent_info, rent);
+}
+
+#[test]
+fn shared_program_cache_works() {
+    let mut warm = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let cache = warm.shared_program_cache().unwrap();
+    let result = warm
+        .program_cache(cache.clone())
+        .program_path(Some(get_program_path("hello-solana")))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert!(warm.last_build_cache_stats().misses > 0);
+
+    // the program compiled by the other builder is reused
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_cache(cache)
+        .program_path(Some(get_program_path("hello-solana")));
+    for _ in 0..2 {
+        let result = builder.build().expect("Failed to build transaction");
+        assert!(result.execution_results[0].was_executed_successfully());
+        let stats = builder.last_build_cache_stats();
+        assert!(stats.hits > 0);
+        assert_eq!(stats.misses, 0);
+    }
+
+    // a changed buffer is compiled again
+    let result = builder
+        .program_path(Some(get_program_path("clock-sysvar")))
+        .build()
+        .expect("Failed to build transaction");
+    let details = result.execution_results[0].details().unwrap();
+    assert!(details.status.is_ok());
+    assert!(details.return_data.is_some());
+    assert!(builder.last_build_cache_stats().misses > 0);
+}
+
+#[test]
+fn lamports_per_signature_works() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .lamports_per_signature(1000)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    let fee_payer = result.fee_payer().fee_payer;
+    assert_eq!(result.fee_payer().required_fee, 1000);
+    assert_eq!(result.lamport_deltas(), vec![(fee_payer, -1000)]);
+
+    let result = builder
+        .lamports_per_signature(0)
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert_eq!(result.fee_payer().required_fee, 0);
+    let fee_payer = result.fee_payer().fee_payer;
+    assert_eq!(result.lamport_deltas(), vec![(fee_payer, 0)]);
+
+    // an explicit check result is charged with the default fee structure
+    let result = builder
+        .lamports_per_signature(1000)
+        .check_result(Ok(CheckedTransactionDetails {
+            nonce: None,
+            lamports_per_signature: 20,
+        }))
+        .build()
+        .expect("Failed to build transaction");
+    let fee_payer = result.fee_payer().fee_payer;
+    let required_fee = FeeStructure::default().lamports_per_signature;
+    assert_eq!(result.fee_payer().required_fee, required_fee);
+    assert_eq!(
+        result.lamport_deltas(),
+        vec![(fee_payer, -(required_fee as i64))]
+    );
+}
+
+#[test]
+fn nonce_account_works() {
+    let address = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let (result, transaction) = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .nonce_account(address, authority)
+        .build_ex()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+
+    let durable_nonce = |account: &AccountSharedData| {
+        let versions: nonce::state::Versions = bincode::deserialize(account.data()).unwrap();
+        match versions.state() {
+            nonce::State::Initialized(data) => data.blockhash(),
+            nonce::State::Uninitialized => panic!("uninitialized nonce account"),
+        }
+    };
+    let stored = builder
+        .get_bank()
+        .get_account_shared_data(&address)
+        .unwrap();
+    assert_eq!(
+        *transaction.message.recent_blockhash(),
+        durable_nonce(&stored)
+    );
+    let advance = &transaction.message.instructions()[0];
+    assert_eq!(
+        transaction.message.static_account_keys()[advance.program_id_index as usize],
+        system_program::id()
+    );
+    assert_eq!(
+        bincode::deserialize::<SystemInstruction>(&advance.data).unwrap(),
+        SystemInstruction::AdvanceNonceAccount
+    );
+
+    let advanced = result.loaded_transactions[0]
+        .as_ref()
+        .unwrap()
+        .accounts
+        .iter()
+        .find(|(key, _)| *key == address)
+        .map(|(_, account)| account.clone())
+        .unwrap();
+    assert_ne!(durable_nonce(&advanced), durable_nonce(&stored));
+}
+
+#[test]
+fn account_after_works() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    assert!(builder.account_after(&recipient).is_none());
+    let result = builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+
+    assert_eq!(
+        builder.account_after(&recipient).unwrap().lamports(),
+        900010
+    );
+    assert_eq!(builder.account_after(&sender).unwrap().lamports(), 899990);
+    // the build is not committed to the bank
+    assert_eq!(
+        builder
+            .get_bank()
+            .get_account_shared_data(&recipient)
+            .unwrap()
+            .lamports(),
+        900000
+    );
+    assert!(builder.account_after(&system_account).is_some());
+    assert!(builder.account_after(&Pubkey::new_unique()).is_none());
+}
+
+#[test]
+fn builtins_subset_works() {
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .compute_unit_limit(200_000)
+        .builtins(BuiltinSet::all().without(Builtin::ComputeBudget))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(!result.execution_results[0].was_executed_successfully());
+    assert!(builder
+        .get_bank()
+        .get_account_shared_data(&Builtin::ComputeBudget.program_id())
+        .is_none());
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    let result = builder
+        .program_path(Some(get_program_path("hello-solana")))
+        .compute_unit_limit(200_000)
+        .builtins(
+            BuiltinSet::empty()
+                .with(Builtin::BpfLoaderUpgradeable)
+                .with(Builtin::ComputeBudget),
+        )
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn borsh_calldata_works() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let system_account = Pubkey::from([0u8; 32]);
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    // `[u8; N]` is serialized as is, matching the big-endian amount the program expects
+    let result = builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_account, None, false, false)
+        .calldata_borsh(&10u64.to_be_bytes())
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert_eq!(
+        builder.account_after(&recipient).unwrap().lamports(),
+        900010
+    );
+
+    builder
+        .program_path(Some(get_program_path("clock-sysvar")))
+        .build()
+        .expect("Failed to build transaction");
+    let execution = builder.last_execution().unwrap();
+    let timestamp: [u8; 8] = execution.return_data_borsh().unwrap();
+    assert_eq!(timestamp.as_slice(), execution.return_data.as_slice());
+    assert!(execution.return_data_borsh::<[u8; 16]>().is_err());
+}
+
+#[test]
+fn simulate_does_not_persist_accounts() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<MockBankCallback>::init(&Default::default()).unwrap();
+    builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_program::id(), None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10])
+        .upgradeable(None);
+    let slot = builder.get_bank().execution_slot();
+
+    for _ in 0..2 {
+        let result = builder.simulate().expect("Failed to simulate transaction");
+        assert!(result.execution_results[0]
+            .details()
+            .unwrap()
+            .status
+            .is_ok());
+        let (_, recipient_data) = result.loaded_transactions[0]
+            .as_ref()
+            .unwrap()
+            .accounts
+            .iter()
+            .find(|(key, _)| *key == recipient)
+            .unwrap();
+        assert_eq!(recipient_data.lamports(), 900010);
+
+        let bank = builder.get_bank();
+        assert!(bank.get_account_shared_data(&sender).is_none());
+        assert!(bank.get_account_shared_data(&recipient).is_none());
+        assert_eq!(bank.execution_slot(), slot);
+        assert!(builder.get_program_id().is_none());
+    }
+
+    // a regular build afterwards still deploys and executes
+    let result = builder.build().expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert!(builder.get_program_id().is_some());
+}
+
+/// Bank whose clones share their accounts and slot, like clones of `BankWrapper`.
+#[derive(Clone, Default)]
+struct SharedBank(Rc<RefCell<MockBankCallback>>);
+
+impl Init for SharedBank {
+    type Error = Error;
+
+    type Config = MockConfig;
+
+    fn init(_cfg: &Self::Config) -> std::result::Result<Self, Self::Error> {
+        Ok(Default::default())
+    }
+}
+
+impl TransactionProcessingCallback for SharedBank {
+    fn account_matches_owners(&self, account: &Pubkey, owners: &[Pubkey]) -> Option<usize> {
+        self.0.borrow().account_matches_owners(account, owners)
+    }
+
+    fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.0.borrow().get_account_shared_data(pubkey)
+    }
+
+    fn add_builtin_account(&self, name: &str, program_id: &Pubkey) {
+        self.0.borrow().add_builtin_account(name, program_id)
+    }
+}
+
+impl BankOperations for SharedBank {
+    type Pubkey = Pubkey;
+
+    type AccountSharedData = AccountSharedData;
+
+    type Error = Error;
+
+    fn insert_account(
+        &mut self,
+        key: Pubkey,
+        data: AccountSharedData,
+    ) -> std::result::Result<(), Self::Error> {
+        self.0.borrow_mut().insert_account(key, data)
+    }
+
+    fn deploy_program(&mut self, buffer: Vec<u8>) -> std::result::Result<Pubkey, Self::Error> {
+        self.0.borrow_mut().deploy_program(buffer)
+    }
+
+    fn set_clock(&mut self) -> std::result::Result<(), Self::Error> {
+        self.0.borrow_mut().set_clock()
+    }
+
+    fn bump(&mut self) -> std::result::Result<(), Self::Error> {
+        self.0.borrow_mut().bump()
+    }
+}
+
+impl BankInfo for SharedBank {
+    type Hash = Hash;
+
+    type Pubkey = Pubkey;
+
+    type Slot = u64;
+
+    type Error = Error;
+
+    fn last_blockhash(&self) -> Hash {
+        self.0.borrow().last_blockhash()
+    }
+
+    fn execution_slot(&self) -> u64 {
+        self.0.borrow().execution_slot()
+    }
+
+    fn collector_id(&self) -> std::result::Result<Pubkey, Self::Error> {
+        self.0.borrow().collector_id()
+    }
+}
+
+#[test]
+fn simulate_leaves_shared_bank_and_last_build_untouched() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let mut builder = SimpleBuilder::<SharedBank>::init(&Default::default()).unwrap();
+    let bank = builder.get_bank().clone();
+    builder
+        .program_path(Some(get_program_path("simple-transfer")))
+        .account_with_balance(sender, Some(900000), true, true)
+        .account_with_balance(recipient, Some(900000), false, true)
+        .account_with_balance(system_program::id(), None, false, false)
+        .calldata(vec![0, 0, 0, 0, 0, 0, 0, 10]);
+    let slot = bank.execution_slot();
+
+    let result = builder.simulate().expect("Failed to simulate transaction");
+    assert!(result.execution_results[0]
+        .details()
+        .unwrap()
+        .status
+        .is_ok());
+    assert!(bank.get_account_shared_data(&sender).is_none());
+    assert!(bank.get_account_shared_data(&recipient).is_none());
+    assert_eq!(bank.execution_slot(), slot);
+    assert!(builder.last_execution().is_none());
+    assert!(builder.account_after(&recipient).is_none());
+    assert!(builder.required_signers().is_empty());
+
+    builder.build().expect("Failed to build transaction");
+    let last_execution = builder.last_execution().unwrap();
+    let required_signers = builder.required_signers();
+    let slot = bank.execution_slot();
+
+    builder.calldata(vec![0, 0, 0, 0, 0, 0, 0, 20]);
+    let result = builder.simulate().expect("Failed to simulate transaction");
+    let (_, recipient_data) = result.loaded_transactions[0]
+        .as_ref()
+        .unwrap()
+        .accounts
+        .iter()
+        .find(|(key, _)| *key == recipient)
+        .unwrap();
+    assert_eq!(recipient_data.lamports(), 900020);
+    assert_eq!(bank.execution_slot(), slot);
+    assert_eq!(builder.last_execution(), Some(last_execution));
+    assert_eq!(builder.required_signers(), required_signers);
+    assert_eq!(
+        builder.account_after(&recipient).unwrap().lamports(),
+        900010
+    );
+}
+
+/// Treats every slot as a descendant of the slots before it, like `MockForkGraph`.
+#[derive(Default)]
+struct ChainForkGraph;
+
+impl ForkGraph for ChainForkGraph {
+    fn relationship(&self, a: u64, b: u64) -> BlockRelation {
+        match a.cmp(&b) {
+            std::cmp::Ordering::Less => BlockRelation::Ancestor,
+            std::cmp::Ordering::Equal => BlockRelation::Equal,
+            std::cmp::Ordering::Greater => BlockRelation::Descendant,
+        }
+    }
+}
+
+#[test]
+fn custom_fork_graph_works() {
+    let fork_graph = Arc::new(RwLock::new(ChainForkGraph));
+
+    let mut builder =
+        SimpleBuilder::<MockBankCallback, ChainForkGraph>::init(&Default::default()).unwrap();
+    let result = builder
+        .fork_graph(fork_graph.clone())
+        .program_path(Some(get_program_path("hello-solana")))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    // the program cache of the processor refers to the injected fork graph
+    assert_eq!(Arc::weak_count(&fork_graph), 1);
+
+    let cache = builder.shared_program_cache().unwrap();
+    let mut other =
+        SimpleBuilder::<MockBankCallback, ChainForkGraph>::init(&Default::default()).unwrap();
+    let result = other
+        .program_cache(cache)
+        .program_path(Some(get_program_path("hello-solana")))
+        .build()
+        .expect("Failed to build transaction");
+    assert!(result.execution_results[0].was_executed_successfully());
+    assert_eq!(Arc::weak_count(&fork_graph), 1);
+}
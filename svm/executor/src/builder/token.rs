@@ -0,0 +1,68 @@
+use igloo_interface::l2::bank::{BankInfo, BankOperations};
+use solana_program_runtime::loaded_programs::ForkGraph;
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount, WritableAccount},
+    clock::Slot,
+    hash::Hash,
+    instruction::AccountMeta,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+use spl_token::state::{Account as TokenAccount, AccountState};
+
+use crate::{builder::output::BuildOutput, prelude::*};
+
+impl<B, FG> SimpleBuilder<B, FG>
+where
+    B: TransactionProcessingCallback
+        + BankOperations<Pubkey = Pubkey, AccountSharedData = AccountSharedData>
+        + BankInfo<Hash = Hash, Pubkey = Pubkey, Slot = Slot>,
+    FG: ForkGraph,
+{
+    /// Adds an initialized, rent-exempt SPL token account holding `amount` tokens of
+    /// `mint` as a writable account of the transaction and returns its pubkey.
+    pub fn token_account(&mut self, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+        let pubkey = Pubkey::new_unique();
+        let state = TokenAccount {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        };
+        let mut account = AccountSharedData::new(
+            self.rent_sysvar().minimum_balance(TokenAccount::LEN),
+            TokenAccount::LEN,
+            &spl_token::id(),
+        );
+        TokenAccount::pack(state, account.data_as_mut_slice())
+            .expect("token account data has the packed length");
+
+        self.account(AccountMeta::new(pubkey, false), Some(account));
+        pubkey
+    }
+}
+
+impl BuildOutput {
+    /// Token amount held by the SPL token account `pubkey` after execution. Panics if
+    /// `pubkey` is not a writable account of the transaction or not a token account.
+    pub fn token_balance_after(&self, pubkey: &Pubkey) -> u64 {
+        let account = self
+            .loaded_account(pubkey)
+            .or_else(|| self.pre_account(pubkey))
+            .unwrap_or_else(|| panic!("account {pubkey} is not part of the transaction"));
+        assert_eq!(
+            account.owner(),
+            &spl_token::id(),
+            "account {pubkey} is not owned by the token program"
+        );
+        TokenAccount::unpack(account.data())
+            .unwrap_or_else(|e| panic!("account {pubkey} is not a token account: {e}"))
+            .amount
+    }
+}
@@ -1,6 +1,8 @@
-use crate::{env::DEPLOYMENT_SLOT, mock::fork_graph::MockForkGraph};
-use solana_program_runtime::loaded_programs::ProgramCacheEntry;
-use solana_sdk::bpf_loader_upgradeable;
+use std::collections::HashSet;
+
+use crate::env::DEPLOYMENT_SLOT;
+use solana_program_runtime::loaded_programs::{ForkGraph, ProgramCacheEntry};
+use solana_sdk::{bpf_loader_upgradeable, compute_budget, pubkey::Pubkey};
 use solana_svm::{
     transaction_processing_callback::TransactionProcessingCallback,
     transaction_processor::TransactionBatchProcessor,
@@ -8,34 +10,133 @@ use solana_svm::{
 
 const BPF_LOADER_NAME: &str = "solana_bpf_loader_upgradeable_program";
 const SYSTEM_PROGRAM_NAME: &str = "system_program";
+const COMPUTE_BUDGET_PROGRAM_NAME: &str = "compute_budget_program";
+
+/// Builtin programs the processor can be registered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    /// Required to execute deployed programs.
+    BpfLoaderUpgradeable,
+    /// Required for native token transfers and account creation.
+    SystemProgram,
+    /// Required for transactions containing compute budget instructions.
+    ComputeBudget,
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 3] = [
+        Builtin::BpfLoaderUpgradeable,
+        Builtin::SystemProgram,
+        Builtin::ComputeBudget,
+    ];
+
+    pub fn program_id(&self) -> Pubkey {
+        match self {
+            Builtin::BpfLoaderUpgradeable => bpf_loader_upgradeable::id(),
+            Builtin::SystemProgram => solana_system_program::id(),
+            Builtin::ComputeBudget => compute_budget::id(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Builtin::BpfLoaderUpgradeable => BPF_LOADER_NAME,
+            Builtin::SystemProgram => SYSTEM_PROGRAM_NAME,
+            Builtin::ComputeBudget => COMPUTE_BUDGET_PROGRAM_NAME,
+        }
+    }
+
+    fn cache_entry(&self) -> ProgramCacheEntry {
+        let name_len = self.name().len();
+        match self {
+            Builtin::BpfLoaderUpgradeable => ProgramCacheEntry::new_builtin(
+                DEPLOYMENT_SLOT,
+                name_len,
+                solana_bpf_loader_program::Entrypoint::vm,
+            ),
+            Builtin::SystemProgram => ProgramCacheEntry::new_builtin(
+                DEPLOYMENT_SLOT,
+                name_len,
+                solana_system_program::system_processor::Entrypoint::vm,
+            ),
+            Builtin::ComputeBudget => ProgramCacheEntry::new_builtin(
+                DEPLOYMENT_SLOT,
+                name_len,
+                solana_compute_budget_program::Entrypoint::vm,
+            ),
+        }
+    }
+}
+
+/// Builtins registered by `register_builtins_ex`, all of them by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuiltinSet(HashSet<Builtin>);
+
+impl Default for BuiltinSet {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl BuiltinSet {
+    pub fn all() -> Self {
+        Self(Builtin::ALL.into_iter().collect())
+    }
 
-pub fn register_builtins<CB: TransactionProcessingCallback>(
+    pub fn empty() -> Self {
+        Self(HashSet::new())
+    }
+
+    pub fn with(mut self, builtin: Builtin) -> Self {
+        self.0.insert(builtin);
+        self
+    }
+
+    pub fn without(mut self, builtin: Builtin) -> Self {
+        self.0.remove(&builtin);
+        self
+    }
+
+    pub fn contains(&self, builtin: Builtin) -> bool {
+        self.0.contains(&builtin)
+    }
+}
+
+pub fn register_builtins<CB: TransactionProcessingCallback, FG: ForkGraph>(
     mock_bank: &CB,
-    batch_processor: &TransactionBatchProcessor<MockForkGraph>,
+    batch_processor: &TransactionBatchProcessor<FG>,
+) {
+    register_builtins_ex(mock_bank, batch_processor, &BuiltinSet::all())
+}
+
+/// Same as `register_builtins`, but only registers the builtins of `builtins`, e.g. to
+/// test how programs cope with a missing builtin.
+pub fn register_builtins_ex<CB: TransactionProcessingCallback, FG: ForkGraph>(
+    mock_bank: &CB,
+    batch_processor: &TransactionBatchProcessor<FG>,
+    builtins: &BuiltinSet,
 ) {
     // We must register the bpf loader account as a loadable account, otherwise programs
-    // won't execute.
-    batch_processor.add_builtin(
-        mock_bank,
-        bpf_loader_upgradeable::id(),
-        BPF_LOADER_NAME,
-        ProgramCacheEntry::new_builtin(
-            DEPLOYMENT_SLOT,
-            BPF_LOADER_NAME.len(),
-            solana_bpf_loader_program::Entrypoint::vm,
-        ),
-    );
-
-    // In order to perform a transference of native tokens using the system instruction,
-    // the system program builtin must be registered.
-    batch_processor.add_builtin(
-        mock_bank,
-        solana_system_program::id(),
-        SYSTEM_PROGRAM_NAME,
-        ProgramCacheEntry::new_builtin(
-            DEPLOYMENT_SLOT,
-            SYSTEM_PROGRAM_NAME.len(),
-            solana_system_program::system_processor::Entrypoint::vm,
-        ),
-    );
+    // won't execute. In order to perform a transference of native tokens using the
+    // system instruction, the system program builtin must be registered. Compute budget
+    // instructions are handled by the runtime, but the program still has to be loadable
+    // for transactions that contain them.
+    for builtin in Builtin::ALL {
+        if builtins.contains(builtin) {
+            batch_processor.add_builtin(
+                mock_bank,
+                builtin.program_id(),
+                builtin.name(),
+                builtin.cache_entry(),
+            );
+        }
+    }
+}
+
+/// Adds the accounts of the builtins registered by `register_builtins` to `mock_bank`, so
+/// a processor registered with another bank can execute transactions against it.
+pub fn register_builtin_accounts<CB: TransactionProcessingCallback>(mock_bank: &CB) {
+    for builtin in Builtin::ALL {
+        mock_bank.add_builtin_account(builtin.name(), &builtin.program_id());
+    }
 }
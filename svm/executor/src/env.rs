@@ -1,24 +1,105 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
-use crate::mock::fork_graph::MockForkGraph;
 use solana_bpf_loader_program::syscalls::{
-    SyscallAbort, SyscallGetClockSysvar, SyscallInvokeSignedRust, SyscallLog, SyscallMemcpy,
-    SyscallMemset, SyscallSetReturnData,
+    SyscallAbort, SyscallError, SyscallGetClockSysvar, SyscallInvokeSignedRust, SyscallLog,
+    SyscallMemcpy, SyscallMemset, SyscallSetReturnData,
 };
 use solana_compute_budget::compute_budget::ComputeBudget;
 use solana_program_runtime::{
     invoke_context::InvokeContext,
-    loaded_programs::{ProgramCache, ProgramRuntimeEnvironments},
+    loaded_programs::{ForkGraph, ProgramCache, ProgramRuntimeEnvironments},
     solana_rbpf::{
+        error::{EbpfError, ProgramResult},
         program::{BuiltinFunction, BuiltinProgram, FunctionRegistry},
-        vm::Config,
+        vm::{Config, EbpfVm},
     },
 };
+use solana_sdk::program::MAX_RETURN_DATA;
 
 pub const DEPLOYMENT_SLOT: u64 = 0;
 pub const DEPLOYMENT_EPOCH: u64 = 0;
 
+thread_local! {
+    static SYSCALL_COUNTS: RefCell<HashMap<&'static [u8], u64>> = RefCell::default();
+    static RETURN_DATA_CAP: Cell<u64> = const { Cell::new(MAX_RETURN_DATA as u64) };
+}
+
+/// Limits the return data of programs executed on the current thread to `cap` bytes.
+/// Caps above the runtime's `MAX_RETURN_DATA` have no effect.
+pub fn set_return_data_cap(cap: usize) {
+    RETURN_DATA_CAP.with(|value| value.set(cap as u64));
+}
+
+/// `sol_set_return_data` failing with `ReturnDataTooLarge` above the cap set with
+/// `set_return_data_cap`, before the runtime's own limit applies.
+struct SyscallSetReturnDataCapped;
+
+impl SyscallSetReturnDataCapped {
+    fn vm(vm: *mut EbpfVm<InvokeContext>, addr: u64, len: u64, arg3: u64, arg4: u64, arg5: u64) {
+        let cap = RETURN_DATA_CAP.with(Cell::get);
+        if len > cap {
+            // SAFETY: the VM passes a valid pointer to itself to every syscall
+            let vm = unsafe { &mut *vm };
+            vm.program_result = ProgramResult::Err(EbpfError::SyscallError(Box::new(
+                SyscallError::ReturnDataTooLarge(len, cap),
+            )));
+            return;
+        }
+        SyscallSetReturnData::vm(vm, addr, len, arg3, arg4, arg5)
+    }
+}
+
+/// Registers `$syscall` under `$name`. With `$count` every invocation is tallied for
+/// `syscall_counts` before the syscall runs.
+macro_rules! register_syscall {
+    ($registry:expr, $count:expr, $name:literal, $syscall:ty) => {{
+        fn counted(
+            vm: *mut EbpfVm<InvokeContext>,
+            arg1: u64,
+            arg2: u64,
+            arg3: u64,
+            arg4: u64,
+            arg5: u64,
+        ) {
+            SYSCALL_COUNTS.with(|counts| *counts.borrow_mut().entry($name).or_default() += 1);
+            <$syscall>::vm(vm, arg1, arg2, arg3, arg4, arg5)
+        }
+
+        let function: BuiltinFunction<InvokeContext> =
+            if $count { counted } else { <$syscall>::vm };
+        $registry
+            .register_function_hashed(*$name, function)
+            .expect("Registration failed");
+    }};
+}
+
+/// Invocations of every syscall on the current thread since the last
+/// `reset_syscall_counts`, as tallied by an environment created with `count_syscalls`.
+pub fn syscall_counts() -> HashMap<String, u64> {
+    SYSCALL_COUNTS.with(|counts| {
+        counts
+            .borrow()
+            .iter()
+            .map(|(name, count)| (String::from_utf8_lossy(name).into_owned(), *count))
+            .collect()
+    })
+}
+
+pub fn reset_syscall_counts() {
+    SYSCALL_COUNTS.with(|counts| counts.borrow_mut().clear());
+}
+
 pub fn create_custom_environment<'a>() -> BuiltinProgram<InvokeContext<'a>> {
+    create_custom_environment_ex(false)
+}
+
+/// Same as `create_custom_environment`, with `count_syscalls` the syscalls are
+/// instrumented to tally their invocations, at the cost of some overhead.
+pub fn create_custom_environment_ex<'a>(count_syscalls: bool) -> BuiltinProgram<InvokeContext<'a>> {
     let compute_budget = ComputeBudget::default();
     let vm_config = Config {
         max_call_depth: compute_budget.max_call_depth,
@@ -44,37 +125,45 @@ pub fn create_custom_environment<'a>() -> BuiltinProgram<InvokeContext<'a>> {
     // These functions are system calls the compile contract calls during execution, so they
     // need to be registered.
     let mut function_registry = FunctionRegistry::<BuiltinFunction<InvokeContext>>::default();
-    function_registry
-        .register_function_hashed(*b"abort", SyscallAbort::vm)
-        .expect("Registration failed");
-    function_registry
-        .register_function_hashed(*b"sol_log_", SyscallLog::vm)
-        .expect("Registration failed");
-    function_registry
-        .register_function_hashed(*b"sol_memcpy_", SyscallMemcpy::vm)
-        .expect("Registration failed");
-    function_registry
-        .register_function_hashed(*b"sol_memset_", SyscallMemset::vm)
-        .expect("Registration failed");
-
-    function_registry
-        .register_function_hashed(*b"sol_invoke_signed_rust", SyscallInvokeSignedRust::vm)
-        .expect("Registration failed");
-
-    function_registry
-        .register_function_hashed(*b"sol_set_return_data", SyscallSetReturnData::vm)
-        .expect("Registration failed");
-
-    function_registry
-        .register_function_hashed(*b"sol_get_clock_sysvar", SyscallGetClockSysvar::vm)
-        .expect("Registration failed");
+    register_syscall!(function_registry, count_syscalls, b"abort", SyscallAbort);
+    register_syscall!(function_registry, count_syscalls, b"sol_log_", SyscallLog);
+    register_syscall!(
+        function_registry,
+        count_syscalls,
+        b"sol_memcpy_",
+        SyscallMemcpy
+    );
+    register_syscall!(
+        function_registry,
+        count_syscalls,
+        b"sol_memset_",
+        SyscallMemset
+    );
+    register_syscall!(
+        function_registry,
+        count_syscalls,
+        b"sol_invoke_signed_rust",
+        SyscallInvokeSignedRust
+    );
+    register_syscall!(
+        function_registry,
+        count_syscalls,
+        b"sol_set_return_data",
+        SyscallSetReturnDataCapped
+    );
+    register_syscall!(
+        function_registry,
+        count_syscalls,
+        b"sol_get_clock_sysvar",
+        SyscallGetClockSysvar
+    );
 
     BuiltinProgram::new_loader(vm_config, function_registry)
 }
 
-pub fn create_executable_environment(
-    fork_graph: Arc<RwLock<MockForkGraph>>,
-    program_cache: &mut ProgramCache<MockForkGraph>,
+pub fn create_executable_environment<FG: ForkGraph>(
+    fork_graph: Arc<RwLock<FG>>,
+    program_cache: &mut ProgramCache<FG>,
 ) {
     program_cache.environments = ProgramRuntimeEnvironments {
         program_runtime_v1: Arc::new(create_custom_environment()),
@@ -14,6 +14,7 @@ use {
         feature_set::FeatureSet,
         native_loader,
         pubkey::Pubkey,
+        rent::Rent,
         sysvar::SysvarId,
     },
     solana_svm::transaction_processing_callback::TransactionProcessingCallback,
@@ -140,7 +141,7 @@ impl BankOperations for MockBankCallback {
             .expect("Time went backwards")
             .as_secs() as i64;
         let clock = Clock {
-            slot: DEPLOYMENT_SLOT,
+            slot: self.execution_slot,
             epoch_start_timestamp: time_now.saturating_sub(10) as UnixTimestamp,
             epoch: DEPLOYMENT_EPOCH,
             leader_schedule_epoch: DEPLOYMENT_EPOCH,
@@ -150,11 +151,17 @@ impl BankOperations for MockBankCallback {
         let mut account_data = AccountSharedData::default();
         account_data.set_data(bincode::serialize(&clock).unwrap());
         self.insert_account(Clock::id(), account_data)?;
+
+        // The upgradeable loader reads the rent sysvar, so provide it together with the clock
+        let mut account_data = AccountSharedData::default();
+        account_data.set_data(bincode::serialize(&Rent::default()).unwrap());
+        self.insert_account(Rent::id(), account_data)?;
         Ok(())
     }
 
     fn bump(&mut self) -> Result<(), Self::Error> {
-        // do nothing by default
+        // Programs deployed or upgraded in a slot only become visible in the next one
+        self.execution_slot += 1;
         Ok(())
     }
 }
@@ -1,10 +1,12 @@
 use {
     solana_sdk::{
+        address_lookup_table::AddressLookupTableAccount,
         hash::Hash,
-        instruction::{AccountMeta, CompiledInstruction},
+        instruction::{AccountMeta, CompiledInstruction, Instruction},
         message::{
             v0::{self, LoadedAddresses, MessageAddressTableLookup},
-            AddressLoader, AddressLoaderError, Message, MessageHeader, VersionedMessage,
+            AddressLoader, AddressLoaderError, CompileError, Message, MessageHeader,
+            VersionedMessage,
         },
         pubkey::Pubkey,
         reserved_account_keys::ReservedAccountKeys,
@@ -27,6 +29,8 @@ pub struct SanitizedTransactionBuilder {
     signed_mutable_accounts: Vec<(Pubkey, Signature)>,
     unsigned_readonly_accounts: Vec<Pubkey>,
     unsigned_mutable_account: Vec<Pubkey>,
+    canonical_account_order: bool,
+    address_lookup_tables: Vec<AddressLookupTableAccount>,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone)]
@@ -43,19 +47,40 @@ struct InnerInstruction {
     data: Vec<u8>,
 }
 
-#[derive(Clone)]
-struct MockLoader {}
+/// Resolves address table lookups against the given tables instead of the bank.
+#[derive(Clone, Copy)]
+struct MockLoader<'a> {
+    tables: &'a [AddressLookupTableAccount],
+}
 
-// This implementation is only necessary if one is using account table lookups.
-impl AddressLoader for MockLoader {
+impl AddressLoader for MockLoader<'_> {
     fn load_addresses(
         self,
-        _lookups: &[MessageAddressTableLookup],
+        lookups: &[MessageAddressTableLookup],
     ) -> Result<LoadedAddresses, AddressLoaderError> {
-        Ok(LoadedAddresses {
-            writable: vec![],
-            readonly: vec![],
-        })
+        let mut loaded = LoadedAddresses::default();
+        for lookup in lookups {
+            let table = self
+                .tables
+                .iter()
+                .find(|table| table.key == lookup.account_key)
+                .ok_or(AddressLoaderError::LookupTableAccountNotFound)?;
+            let resolve = |indexes: &[u8]| {
+                indexes
+                    .iter()
+                    .map(|index| {
+                        table
+                            .addresses
+                            .get(*index as usize)
+                            .copied()
+                            .ok_or(AddressLoaderError::InvalidLookupIndex)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            loaded.writable.extend(resolve(&lookup.writable_indexes)?);
+            loaded.readonly.extend(resolve(&lookup.readonly_indexes)?);
+        }
+        Ok(loaded)
     }
 }
 
@@ -107,12 +132,30 @@ impl SanitizedTransactionBuilder {
         self.instructions.push(instruction);
     }
 
+    /// Sorts the non-signer accounts of every group by pubkey instead of keeping their
+    /// insertion order, so the serialized transaction is stable across runs. This is
+    /// for test stability only and may not match the ordering of a specific wallet.
+    pub fn canonical_account_order(&mut self, value: bool) {
+        self.canonical_account_order = value;
+    }
+
+    /// Address lookup tables v0 messages look up their non-signer accounts in, accounts
+    /// missing from the tables stay in the static account keys. Legacy messages ignore
+    /// the tables.
+    pub fn address_lookup_tables(&mut self, tables: Vec<AddressLookupTableAccount>) {
+        self.address_lookup_tables = tables;
+    }
+
     pub fn build(
         &mut self,
         block_hash: Hash,
         fee_payer: (Pubkey, Signature),
         v0_message: bool,
     ) -> Result<(SanitizedTransaction, VersionedTransaction), TransactionError> {
+        if v0_message && !self.address_lookup_tables.is_empty() {
+            return self.build_with_lookups(block_hash, fee_payer);
+        }
+
         let mut account_keys = Vec::with_capacity(
             self.signed_mutable_accounts
                 .len()
@@ -142,6 +185,11 @@ impl SanitizedTransactionBuilder {
         account_keys.push(fee_payer.0);
         signatures.push(fee_payer.1);
 
+        if self.canonical_account_order {
+            self.unsigned_mutable_account.sort();
+            self.unsigned_readonly_accounts.sort();
+        }
+
         let mut positions_lambda = |key: &Pubkey, ty: AccountType| {
             positions.insert((*key, ty), account_keys.len());
             account_keys.push(*key);
@@ -204,26 +252,113 @@ impl SanitizedTransactionBuilder {
             VersionedMessage::Legacy(message)
         };
 
-        let transaction = VersionedTransaction {
-            signatures,
-            message,
+        sanitize(
+            VersionedTransaction {
+                signatures,
+                message,
+            },
+            &[],
+        )
+    }
+
+    /// Compiles the created instructions against the lookup tables, the account order
+    /// then follows the message compiler rather than the creation order.
+    fn build_with_lookups(
+        &mut self,
+        block_hash: Hash,
+        fee_payer: (Pubkey, Signature),
+    ) -> Result<(SanitizedTransaction, VersionedTransaction), TransactionError> {
+        let mut signers = HashMap::from([fee_payer]);
+        signers.extend(self.signed_mutable_accounts.iter().copied());
+        signers.extend(self.signed_readonly_accounts.iter().copied());
+        let instructions = self
+            .clean_up()
+            .into_iter()
+            .map(|item| Instruction {
+                program_id: item.program_id,
+                accounts: item
+                    .accounts
+                    .into_iter()
+                    .map(|(pubkey, ty)| AccountMeta {
+                        pubkey,
+                        is_signer: matches!(
+                            ty,
+                            AccountType::SignerReadonly | AccountType::SignerWritable
+                        ),
+                        is_writable: matches!(
+                            ty,
+                            AccountType::Writable | AccountType::SignerWritable
+                        ),
+                    })
+                    .collect(),
+                data: item.data,
+            })
+            .collect::<Vec<_>>();
+
+        let message =
+            VersionedMessage::V0(self.compile_v0(&fee_payer.0, &instructions, block_hash)?);
+        let signatures = message.static_account_keys()
+            [..message.header().num_required_signatures as usize]
+            .iter()
+            .map(|key| signers[key])
+            .collect();
+
+        sanitize(
+            VersionedTransaction {
+                signatures,
+                message,
+            },
+            &self.address_lookup_tables,
+        )
+    }
+
+    fn compile_v0(
+        &self,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        block_hash: Hash,
+    ) -> Result<v0::Message, TransactionError> {
+        v0::Message::try_compile(payer, instructions, &self.address_lookup_tables, block_hash)
+            .map_err(|e| match e {
+                CompileError::AccountIndexOverflow
+                | CompileError::AddressTableLookupIndexOverflow => {
+                    TransactionError::TooManyAccountLocks
+                }
+                _ => TransactionError::SanitizeFailure,
+            })
+    }
+
+    /// Builds a transaction from complete instructions, deriving the account set and the
+    /// message header from their account metas instead of `create_instruction`.
+    pub fn build_from_instructions(
+        &self,
+        instructions: &[Instruction],
+        block_hash: Hash,
+        fee_payer: (Pubkey, Signature),
+        v0_message: bool,
+    ) -> Result<(SanitizedTransaction, VersionedTransaction), TransactionError> {
+        let message = if v0_message {
+            VersionedMessage::V0(self.compile_v0(&fee_payer.0, instructions, block_hash)?)
+        } else {
+            VersionedMessage::Legacy(Message::new_with_blockhash(
+                instructions,
+                Some(&fee_payer.0),
+                &block_hash,
+            ))
         };
 
-        let sanitized_versioned_transaction =
-            SanitizedVersionedTransaction::try_new(transaction.clone()).unwrap();
-
-        let loader = MockLoader {};
-
-        Ok((
-            SanitizedTransaction::try_new(
-                sanitized_versioned_transaction,
-                Hash::new_unique(),
-                false,
-                loader,
-                &ReservedAccountKeys::new_all_activated().active,
-            )?,
-            transaction,
-        ))
+        // the fee payer always comes first
+        let mut signatures = vec![fee_payer.1];
+        signatures
+            .extend((1..message.header().num_required_signatures).map(|_| Signature::new_unique()));
+
+        sanitize(
+            VersionedTransaction {
+                signatures,
+                message,
+            },
+            &self.address_lookup_tables,
+        )
     }
 
     fn clean_up(&mut self) -> Vec<InnerInstruction> {
@@ -236,12 +371,52 @@ impl SanitizedTransactionBuilder {
         self.signed_mutable_accounts.clear();
         self.signed_readonly_accounts.clear();
         self.unsigned_mutable_account.clear();
-        self.unsigned_mutable_account.clear();
+        self.unsigned_readonly_accounts.clear();
 
         instructions
     }
 }
 
+/// Sanitizes a transaction built elsewhere, see `sanitize_transaction_with_lookups` for
+/// transactions with address table lookups.
+pub fn sanitize_transaction(
+    transaction: VersionedTransaction,
+) -> Result<SanitizedTransaction, TransactionError> {
+    sanitize_transaction_with_lookups(transaction, &[])
+}
+
+/// Sanitizes a transaction built elsewhere, resolving its address table lookups against
+/// `tables`.
+pub fn sanitize_transaction_with_lookups(
+    transaction: VersionedTransaction,
+    tables: &[AddressLookupTableAccount],
+) -> Result<SanitizedTransaction, TransactionError> {
+    sanitize(transaction, tables).map(|(sanitized, _)| sanitized)
+}
+
+fn sanitize(
+    transaction: VersionedTransaction,
+    tables: &[AddressLookupTableAccount],
+) -> Result<(SanitizedTransaction, VersionedTransaction), TransactionError> {
+    let sanitized_versioned_transaction =
+        SanitizedVersionedTransaction::try_new(transaction.clone())?;
+
+    let loader = MockLoader { tables };
+    // the hash signers sign, computed the same way for legacy and v0 messages
+    let message_hash = transaction.message.hash();
+
+    Ok((
+        SanitizedTransaction::try_new(
+            sanitized_versioned_transaction,
+            message_hash,
+            false,
+            loader,
+            &ReservedAccountKeys::new_all_activated().active,
+        )?,
+        transaction,
+    ))
+}
+
 fn push_and_return_index(value: Pubkey, vector: &mut Vec<Pubkey>) -> u8 {
     vector.push(value);
     vector.len().saturating_sub(1) as u8